@@ -0,0 +1,216 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// Timing of an animated image. Single-frame files aren't animations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub frame_count: i64,
+    /// Times the animation plays; 0 loops forever
+    pub loop_count: i64,
+    pub duration_sec: f64,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn skip<R: Read>(r: &mut R, len: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut r.take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn read_array<const N: usize, R: Read>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Frames, loops and total duration of a GIF, animated WebP or APNG; `None` for stills
+/// and for formats that can't animate.
+pub fn inspect(path: &Path, format_id: &str) -> io::Result<Option<Animation>> {
+    let mut r = BufReader::new(File::open(path)?);
+    let animation = match format_id {
+        "gif" => gif(&mut r)?,
+        "png" => apng(&mut r)?,
+        "webp" => webp(&mut r)?,
+        _ => return Ok(None),
+    };
+    Ok(animation.filter(|a| a.frame_count > 1))
+}
+
+/// Walks the GIF block stream without decoding any pixels.
+fn gif<R: Read>(r: &mut R) -> io::Result<Option<Animation>> {
+    let header: [u8; 13] = read_array(r)?;
+    if !header.starts_with(b"GIF8") {
+        return Err(invalid("Not a GIF"));
+    }
+    if header[10] & 0x80 != 0 {
+        skip(r, 3 << ((header[10] & 0x07) + 1))?;
+    }
+
+    let mut frames = 0i64;
+    let mut centiseconds = 0u64;
+    // Without a NETSCAPE extension a GIF plays once
+    let mut loop_count = 1i64;
+
+    loop {
+        let [block] = read_array(r)?;
+        match block {
+            // Image descriptor
+            0x2C => {
+                let descriptor: [u8; 9] = read_array(r)?;
+                if descriptor[8] & 0x80 != 0 {
+                    skip(r, 3 << ((descriptor[8] & 0x07) + 1))?;
+                }
+                skip(r, 1)?; // LZW minimum code size
+                skip_sub_blocks(r)?;
+                frames += 1;
+            }
+            // Extension
+            0x21 => {
+                let [label] = read_array(r)?;
+                match label {
+                    0xF9 => {
+                        let gce: [u8; 5] = read_array(r)?;
+                        let delay = u16::from_le_bytes([gce[2], gce[3]]) as u64;
+                        // Browsers play 0 and 1 as 10 (100 ms); so do we
+                        centiseconds += if delay < 2 { 10 } else { delay };
+                        skip_sub_blocks(r)?;
+                    }
+                    0xFF => {
+                        let app: [u8; 12] = read_array(r)?;
+                        if &app[1..12] == b"NETSCAPE2.0" || &app[1..12] == b"ANIMEXTS1.0" {
+                            let data: [u8; 4] = read_array(r)?;
+                            if data[0] >= 3 && data[1] == 1 {
+                                // Repetitions after the first play
+                                let repeats = u16::from_le_bytes([data[2], data[3]]) as i64;
+                                loop_count = if repeats == 0 { 0 } else { repeats + 1 };
+                            }
+                            if data[0] > 3 {
+                                skip(r, data[0] as u64 - 3)?;
+                            }
+                        }
+                        skip_sub_blocks(r)?;
+                    }
+                    _ => skip_sub_blocks(r)?,
+                }
+            }
+            0x3B => break,
+            _ => return Err(invalid("Bad GIF block")),
+        }
+    }
+
+    Ok(Some(Animation {
+        frame_count: frames,
+        loop_count,
+        duration_sec: centiseconds as f64 / 100.0,
+    }))
+}
+
+fn skip_sub_blocks<R: Read>(r: &mut R) -> io::Result<()> {
+    loop {
+        let [len] = read_array(r)?;
+        if len == 0 {
+            return Ok(());
+        }
+        skip(r, len as u64)?;
+    }
+}
+
+/// APNG frames are announced by `acTL` and timed by one `fcTL` per frame.
+fn apng<R: Read>(r: &mut R) -> io::Result<Option<Animation>> {
+    let signature: [u8; 8] = read_array(r)?;
+    if &signature != b"\x89PNG\r\n\x1a\n" {
+        return Err(invalid("Not a PNG"));
+    }
+
+    let mut control: Option<(i64, i64)> = None;
+    let mut duration = 0.0;
+    loop {
+        let head: [u8; 8] = read_array(r)?;
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as u64;
+        match &head[4..8] {
+            b"acTL" if len >= 8 => {
+                let data: [u8; 8] = read_array(r)?;
+                let frames = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as i64;
+                let plays = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as i64;
+                control = Some((frames, plays));
+                skip(r, len - 8 + 4)?;
+            }
+            b"fcTL" if len >= 26 => {
+                let data: [u8; 26] = read_array(r)?;
+                let num = u16::from_be_bytes([data[20], data[21]]) as f64;
+                let den = match u16::from_be_bytes([data[22], data[23]]) {
+                    0 => 100.0, // Per the spec, 0 means hundredths
+                    d => d as f64,
+                };
+                duration += num / den;
+                skip(r, len - 26 + 4)?;
+            }
+            // No acTL before the image data: a plain PNG
+            b"IDAT" if control.is_none() => return Ok(None),
+            b"IEND" => break,
+            _ => skip(r, len + 4)?,
+        }
+    }
+
+    Ok(control.map(|(frame_count, loop_count)| Animation {
+        frame_count,
+        loop_count,
+        duration_sec: duration,
+    }))
+}
+
+/// Animated WebP is an extended (`VP8X`) file with an `ANIM` chunk and one `ANMF` per frame.
+fn webp<R: Read>(r: &mut R) -> io::Result<Option<Animation>> {
+    let header: [u8; 12] = read_array(r)?;
+    if !header.starts_with(b"RIFF") || &header[8..12] != b"WEBP" {
+        return Err(invalid("Not a WebP"));
+    }
+
+    let mut loop_count = 0i64;
+    let mut frames = 0i64;
+    let mut milliseconds = 0u64;
+    loop {
+        let head: [u8; 8] = match read_array(r) {
+            Ok(head) => head,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let len = u32::from_le_bytes([head[4], head[5], head[6], head[7]]) as u64;
+        let padded = len + (len & 1);
+        match &head[0..4] {
+            b"VP8X" if len >= 1 => {
+                let [flags] = read_array(r)?;
+                if flags & 0x02 == 0 {
+                    return Ok(None);
+                }
+                skip(r, padded - 1)?;
+            }
+            // Simple (lossy or lossless) files hold exactly one image
+            b"VP8 " | b"VP8L" if frames == 0 => return Ok(None),
+            b"ANIM" if len >= 6 => {
+                let data: [u8; 6] = read_array(r)?;
+                loop_count = u16::from_le_bytes([data[4], data[5]]) as i64;
+                skip(r, padded - 6)?;
+            }
+            b"ANMF" if len >= 16 => {
+                let data: [u8; 16] = read_array(r)?;
+                milliseconds += u32::from_le_bytes([data[12], data[13], data[14], 0]) as u64;
+                frames += 1;
+                skip(r, padded - 16)?;
+            }
+            _ => skip(r, padded)?,
+        }
+    }
+
+    Ok(Some(Animation {
+        frame_count: frames,
+        loop_count,
+        duration_sec: milliseconds as f64 / 1000.0,
+    }))
+}
@@ -0,0 +1,267 @@
+use crate::db::{self, changes};
+use rusqlite::{params, Connection, DatabaseName};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const INTERVAL_SETTING: &str = "backup_interval_hours";
+pub const KEEP_SETTING: &str = "backup_keep";
+pub const DEFAULT_INTERVAL_HOURS: i64 = 24;
+pub const DEFAULT_KEEP: i64 = 7;
+
+const PREFIX: &str = "xcroller-";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupInfo {
+    pub path: String,
+    pub created_at: i64,
+    pub size_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupSchedule {
+    pub interval_hours: i64, // 0 disables scheduled backups
+    pub keep: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptimizeReport {
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OrphanReport {
+    /// Rows outside every library folder whose file is still there; kept, not removed
+    pub outside_folders: usize,
+    /// Rows whose file is gone while its folder (or, outside the library, its directory) is still reachable
+    pub missing_files: usize,
+    /// Cached thumbnails for items that no longer exist
+    pub thumbnails: usize,
+}
+
+pub fn backup_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups")
+}
+
+fn setting(conn: &Connection, key: &str, default: i64) -> i64 {
+    changes::get_setting(conn, key)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+pub fn schedule(conn: &Connection) -> BackupSchedule {
+    BackupSchedule {
+        interval_hours: setting(conn, INTERVAL_SETTING, DEFAULT_INTERVAL_HOURS),
+        keep: setting(conn, KEEP_SETTING, DEFAULT_KEEP),
+    }
+}
+
+pub fn set_schedule(conn: &Connection, schedule: &BackupSchedule) -> Result<(), String> {
+    if schedule.interval_hours < 0 || schedule.keep < 1 {
+        return Err("Keep at least one backup; the interval can't be negative".to_string());
+    }
+    changes::set_setting(conn, INTERVAL_SETTING, &schedule.interval_hours.to_string())
+        .and_then(|_| changes::set_setting(conn, KEEP_SETTING, &schedule.keep.to_string()))
+        .map_err(|e| e.to_string())
+}
+
+/// Newest first.
+pub fn list_backups(dir: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<BackupInfo> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // "xcroller-<unix time>.db"; the time is in the name so copies keep it
+            let created_at = name.strip_prefix(PREFIX)?.strip_suffix(".db")?.parse().ok()?;
+            Some(BackupInfo {
+                path: entry.path().to_string_lossy().to_string(),
+                created_at,
+                size_bytes: entry.metadata().map(|m| m.len() as i64).unwrap_or(0),
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    backups
+}
+
+/// Copies the live database with SQLite's online backup API, so the app can keep writing meanwhile.
+pub fn create_backup(conn: &Connection, dir: &Path) -> Result<BackupInfo, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let mut created_at = db::unix_now();
+    // Two backups in the same second would share a name
+    while dir.join(format!("{}{}.db", PREFIX, created_at)).exists() {
+        created_at += 1;
+    }
+    let path = dir.join(format!("{}{}.db", PREFIX, created_at));
+
+    conn.backup(DatabaseName::Main, &path, None)
+        .map_err(|e| format!("Backup failed: {}", e))?;
+
+    Ok(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        created_at,
+        size_bytes: fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0),
+    })
+}
+
+/// Deletes all but the newest `keep` backups.
+pub fn rotate(dir: &Path, keep: i64) -> usize {
+    let mut removed = 0;
+    for backup in list_backups(dir).into_iter().skip(keep.max(1) as usize) {
+        if fs::remove_file(&backup.path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Backs up and rotates when the newest backup is older than the configured interval.
+pub fn run_scheduled(conn: &Connection, dir: &Path) -> Result<Option<BackupInfo>, String> {
+    let schedule = schedule(conn);
+    if schedule.interval_hours <= 0 {
+        return Ok(None);
+    }
+    let due = list_backups(dir)
+        .first()
+        .is_none_or(|latest| db::unix_now() - latest.created_at >= schedule.interval_hours * 3600);
+    if !due {
+        return Ok(None);
+    }
+    let backup = create_backup(conn, dir)?;
+    rotate(dir, schedule.keep);
+    Ok(Some(backup))
+}
+
+/// Replaces the live database with `backup_path`. The current state is backed up first,
+/// so a restore can itself be undone.
+pub fn restore_backup(conn: &mut Connection, dir: &Path, backup_path: &Path) -> Result<(), String> {
+    let backups = list_backups(dir);
+    if !backups.iter().any(|b| Path::new(&b.path) == backup_path) {
+        return Err(format!("{} is not one of this library's backups", backup_path.display()));
+    }
+
+    let check = Connection::open(backup_path).map_err(|e| e.to_string())?;
+    let problems = integrity_check(&check)?;
+    if problems != ["ok"] {
+        return Err(format!("The backup is damaged: {}", problems.join("; ")));
+    }
+    drop(check);
+
+    create_backup(conn, dir)?;
+    conn.restore(DatabaseName::Main, backup_path, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| format!("Restore failed: {}", e))?;
+    // Older backups may predate recent migrations
+    db::init_schema(conn).map_err(|e| e.to_string())
+}
+
+/// "ok", or one line per problem SQLite found.
+pub fn integrity_check(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(rows)
+}
+
+fn database_size(conn: &Connection) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn optimize(conn: &Connection) -> Result<OptimizeReport, String> {
+    let size_before = database_size(conn)?;
+    conn.execute_batch("VACUUM; ANALYZE;").map_err(|e| e.to_string())?;
+    Ok(OptimizeReport {
+        size_before,
+        size_after: database_size(conn)?,
+    })
+}
+
+/// Removes rows of missing files and cache files nothing points at any more.
+/// Rows under an unreachable folder (e.g. an unplugged drive) are left alone, and so are rows
+/// outside every folder whose file still exists, such as files a move export relocated.
+pub fn clean_orphans(conn: &Connection, thumb_dir: &Path) -> Result<OrphanReport, String> {
+    // (id, path, root) with no root for rows outside every library folder
+    let rows: Vec<(i64, String, Option<String>)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.id, m.path, f.path FROM media_items m
+                 LEFT JOIN folders f ON f.id = m.folder_id WHERE m.trashed_at IS NULL",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+        rows
+    };
+
+    let mut report = OrphanReport::default();
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    for (id, path, root) in &rows {
+        let file = Path::new(path);
+        // Without a folder, the file's own directory tells a deleted file from an unplugged drive
+        let reachable = match root {
+            Some(root) => Path::new(root).is_dir(),
+            None => file.parent().is_some_and(Path::is_dir),
+        };
+        let orphan = reachable && !file.exists();
+        if orphan {
+            report.missing_files += 1;
+        } else if root.is_none() {
+            report.outside_folders += 1;
+        }
+        if orphan {
+            tx.execute("DELETE FROM media_items WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    // Subfolders with nothing left in them, deepest first
+    while tx
+        .execute(
+            "DELETE FROM directories WHERE rel_path != ''
+             AND NOT EXISTS (SELECT 1 FROM media_items m WHERE m.directory_id = directories.id)
+             AND NOT EXISTS (SELECT 1 FROM directories c WHERE c.parent_id = directories.id)",
+            [],
+        )
+        .map_err(|e| e.to_string())?
+        > 0
+    {}
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // Thumbnails are named "<id>_<size>_<mtime>.jpg"
+    let ids: HashSet<i64> = {
+        let mut stmt = conn.prepare("SELECT id FROM media_items").map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+        ids
+    };
+    if let Ok(entries) = fs::read_dir(thumb_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let id = name.split('_').next().and_then(|id| id.parse::<i64>().ok());
+            if id.is_some_and(|id| !ids.contains(&id)) && fs::remove_file(entry.path()).is_ok() {
+                report.thumbnails += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
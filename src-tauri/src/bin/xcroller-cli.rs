@@ -0,0 +1,399 @@
+//! Headless access to an Xcroller library: scan, query and export without starting the webview.
+
+use clap::{Parser, Subcommand};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use xcroller_lib::{db, export, library, normalize_path, remap, scanner};
+
+// Must match `identifier` in tauri.conf.json so the CLI finds the desktop app's library
+const APP_IDENTIFIER: &str = "com.jens-aerts.xcroller";
+
+#[derive(Parser)]
+#[command(name = "xcroller-cli", version, about = "Headless access to an Xcroller library")]
+struct Cli {
+    /// Library database to use (defaults to the desktop app's active library)
+    #[arg(long, global = true, conflicts_with = "library")]
+    db: Option<PathBuf>,
+
+    /// Library to use, by id or name
+    #[arg(long, global = true)]
+    library: Option<String>,
+
+    /// Print JSON instead of human-readable text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Start a new library database if none exists at the path yet
+    #[arg(long, global = true)]
+    create: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Add a folder to the library and index it
+    Scan {
+        path: String,
+        /// Index subfolders too, and remember it for rescans
+        #[arg(long, conflicts_with = "no_recursive")]
+        recursive: bool,
+        /// Only index files directly inside the folder, and remember it for rescans
+        #[arg(long)]
+        no_recursive: bool,
+    },
+    /// Re-index registered folders with their saved settings (all active ones unless paths are given)
+    Rescan { paths: Vec<String> },
+    /// List the desktop app's libraries
+    Libraries,
+    /// Point a library folder at its new location, keeping stars and metadata
+    Remap { old_root: String, new_root: String },
+    /// List media matching a FilterOptions JSON object
+    Query {
+        #[arg(long, default_value = "{}")]
+        filter_json: String,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+        #[arg(long, default_value_t = 0)]
+        offset: i64,
+    },
+    /// Export favorites (the default), a feed, a filter or specific ids
+    Export {
+        /// Target folder, or archive file for --mode zip
+        target: PathBuf,
+        #[arg(long, conflicts_with_all = ["ids", "filter_json"])]
+        feed: Option<i64>,
+        #[arg(long, value_delimiter = ',', conflicts_with = "filter_json")]
+        ids: Vec<i64>,
+        #[arg(long)]
+        filter_json: Option<String>,
+        /// copy, move, hardlink, symlink or zip
+        #[arg(long, default_value = "copy", value_parser = parse_enum::<export::ExportMode>)]
+        mode: export::ExportMode,
+        /// skip, overwrite, rename or prefix_folder
+        #[arg(long, default_value = "rename", value_parser = parse_enum::<export::CollisionPolicy>)]
+        collision: export::CollisionPolicy,
+        /// Recreate folders below each library root
+        #[arg(long)]
+        preserve_structure: bool,
+        /// json or csv
+        #[arg(long, value_parser = parse_enum::<export::ManifestFormat>)]
+        manifest: Option<export::ManifestFormat>,
+    },
+    /// Inspect saved feeds
+    Feeds {
+        #[command(subcommand)]
+        command: FeedsCommand,
+    },
+    /// Library totals
+    Stats,
+    /// Star (or with --off, unstar) media items
+    Star {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+        #[arg(long)]
+        off: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FeedsCommand {
+    List,
+    /// Show a feed's filters and folders
+    Show { id: i64 },
+}
+
+/// Parses CLI values with the same snake_case names the frontend sends.
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unsupported value \"{}\"", value))
+}
+
+fn parse_filters(json: &str) -> Result<db::FilterOptions, String> {
+    let filters: db::FilterOptions =
+        serde_json::from_str(json).map_err(|e| format!("Invalid --filter-json: {}", e))?;
+    filters.validate().map_err(|e| format!("Invalid --filter-json: {}", e))?;
+    Ok(filters)
+}
+
+fn app_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Could not determine the app data directory; pass --db".to_string())
+}
+
+/// The named library's database, or the one the desktop app has open.
+fn library_db_path(name: Option<&str>) -> Result<PathBuf, String> {
+    let registry = library::Registry::load(&app_dir()?);
+    let library = match name {
+        Some(name) => registry.find(name).ok_or_else(|| format!("No library called {}", name))?,
+        None => registry.active(),
+    };
+    Ok(library.db_path.clone())
+}
+
+fn open_db(path: &Path, create: bool) -> Result<Connection, String> {
+    // A mistyped --db shouldn't quietly start an empty library
+    if !create && !path.exists() {
+        return Err(format!("No library database at {}; pass --create to start one", path.display()));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let conn = db::open(path).map_err(|e| e.to_string())?;
+    db::init_schema(&conn).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn print<T: Serialize>(json: bool, value: &T, human: impl FnOnce(&T)) -> Result<(), String> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).map_err(|e| e.to_string())?);
+    } else {
+        human(value);
+    }
+    Ok(())
+}
+
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let db_path = match cli.db {
+        Some(path) => path,
+        None => library_db_path(cli.library.as_deref())?,
+    };
+    let conn = open_db(&db_path, cli.create)?;
+    let json = cli.json;
+
+    match cli.command {
+        Command::Scan {
+            path,
+            recursive,
+            no_recursive,
+        } => {
+            let path = normalize_path(&path);
+            db::changes::add_folder(&conn, &path).map_err(|e| e.to_string())?;
+            let mut folder = db::changes::get_folder(&conn, &path)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("{} is not a library folder", path))?;
+            // Like the desktop app, keep the saved setting unless a flag asks otherwise
+            if recursive || no_recursive {
+                folder.settings.recursive = recursive;
+                db::changes::set_folder_settings(&conn, &path, &folder.settings).map_err(|e| e.to_string())?;
+            }
+            let count = scanner::rescan_folder(&db_path, &folder)?;
+            print(json, &count, |c| println!("Indexed {} files in {}", c, path))
+        }
+        Command::Rescan { paths } => {
+            let wanted: Vec<String> = paths.iter().map(|p| normalize_path(p)).collect();
+            let folders = db::changes::get_folders(&conn).map_err(|e| e.to_string())?;
+            let mut counts = Vec::new();
+            for folder in folders {
+                // Inactive folders only when asked for by name
+                let skip = if wanted.is_empty() {
+                    !folder.settings.is_active
+                } else {
+                    !wanted.contains(&folder.path)
+                };
+                if skip {
+                    continue;
+                }
+                let count = scanner::rescan_folder(&db_path, &folder)?;
+                counts.push((folder.path, count));
+            }
+            print(json, &counts, |counts| {
+                for (path, count) in counts {
+                    println!("{:>8}  {}", count, path);
+                }
+            })
+        }
+        Command::Libraries => {
+            let registry = library::Registry::load(&app_dir()?);
+            print(json, &registry, |r| {
+                for library in &r.libraries {
+                    let marker = if library.id == r.active { "*" } else { " " };
+                    println!("{} {:<16} {:<20} {}", marker, library.id, library.name, library.db_path.display());
+                }
+            })
+        }
+        Command::Remap { old_root, new_root } => {
+            let report = remap::remap_folder(&conn, &old_root, &new_root)?;
+            print(json, &report, |r| {
+                println!("Moved {} folder(s), {} item(s); updated {} feed(s)", r.folders, r.items, r.feeds)
+            })
+        }
+        Command::Query {
+            filter_json,
+            limit,
+            offset,
+        } => {
+            let filters = parse_filters(&filter_json)?;
+            let items =
+                db::changes::get_media(&conn, limit, offset, filters).map_err(|e| e.to_string())?;
+            print(json, &items, |items| {
+                for item in items {
+                    let size = match (item.width, item.height) {
+                        (Some(w), Some(h)) => format!("{}x{}", w, h),
+                        _ => "-".to_string(),
+                    };
+                    let duration = item
+                        .duration_sec
+                        .map(|d| format!("{:.0}s", d))
+                        .unwrap_or_else(|| "-".to_string());
+                    println!(
+                        "{:>7} {} {:<5} {:>10} {:>6}  {}",
+                        item.id.unwrap_or_default(),
+                        if item.starred { "*" } else { " " },
+                        item.file_type,
+                        size,
+                        duration,
+                        item.path
+                    );
+                }
+            })
+        }
+        Command::Export {
+            target,
+            feed,
+            ids,
+            filter_json,
+            mode,
+            collision,
+            preserve_structure,
+            manifest,
+        } => {
+            let source = if !ids.is_empty() {
+                export::ExportSource::Ids(ids)
+            } else if let Some(id) = feed {
+                export::ExportSource::Feed(id)
+            } else if let Some(filter_json) = filter_json {
+                export::ExportSource::Filters(Box::new(parse_filters(&filter_json)?))
+            } else {
+                export::ExportSource::Favorites
+            };
+            let options = export::ExportOptions {
+                mode,
+                collision,
+                preserve_structure,
+                manifest,
+                transform: None,
+            };
+
+            let items = export::select_items(&conn, &source)?;
+            let roots: Vec<String> = db::changes::get_folders(&conn)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|f| f.path)
+                .collect();
+
+            let progress = |p: export::ExportProgress| {
+                if !json {
+                    eprint!("\r{}/{}", p.done, p.total);
+                }
+            };
+            let mut report = export::export_items(&items, &roots, &target, &options, &progress)?;
+            if !json && !items.is_empty() {
+                eprintln!();
+            }
+            if mode == export::ExportMode::Move {
+                export::record_moves(&conn, &mut report)?;
+            }
+
+            print(json, &report, |r| {
+                for file in r.files.iter().filter(|f| f.status != export::ExportStatus::Exported) {
+                    println!(
+                        "{:?}: {} ({})",
+                        file.status,
+                        file.source,
+                        file.message.as_deref().unwrap_or("")
+                    );
+                }
+                println!(
+                    "Exported {}, skipped {}, failed {}",
+                    r.exported, r.skipped, r.failed
+                );
+                if r.left_library > 0 {
+                    println!("{} moved files are now outside every library folder", r.left_library);
+                }
+            })
+        }
+        Command::Feeds { command } => match command {
+            FeedsCommand::List => {
+                let feeds = db::changes::get_feeds(&conn).map_err(|e| e.to_string())?;
+                print(json, &feeds, |feeds| {
+                    for feed in feeds {
+                        println!("{:>5}  {}", feed.id.unwrap_or_default(), feed.name);
+                    }
+                })
+            }
+            FeedsCommand::Show { id } => {
+                let feed = db::changes::get_feed(&conn, id).map_err(|e| e.to_string())?;
+                let filters = feed.filters().map_err(|e| e.to_string())?;
+                print(json, &(&feed.name, &filters), |(name, filters)| {
+                    println!("{}", name);
+                    for path in filters.folder_paths.iter().flatten() {
+                        println!("  folder: {}", path);
+                    }
+                    println!(
+                        "  filters: {}",
+                        serde_json::to_string(filters).unwrap_or_default()
+                    );
+                })
+            }
+        },
+        Command::Stats => {
+            let summary = db::changes::get_library_summary(&conn).map_err(|e| e.to_string())?;
+            print(json, &summary, |s| {
+                println!("Items:    {} ({} images, {} videos)", s.items, s.images, s.videos);
+                println!("Starred:  {}", s.starred);
+                println!("Size:     {}", format_bytes(s.total_bytes));
+                println!("Folders:  {}", s.folders);
+                println!("Feeds:    {}", s.feeds);
+            })
+        }
+        Command::Star { ids, off } => {
+            let mut changed = Vec::new();
+            let mut unknown = Vec::new();
+            for &id in &ids {
+                if db::changes::set_starred(&conn, id, !off).map_err(|e| e.to_string())? {
+                    changed.push(id);
+                } else {
+                    unknown.push(id);
+                }
+            }
+            if !unknown.is_empty() {
+                eprintln!("No media items with id {:?}", unknown);
+            }
+            print(json, &changed, |changed| {
+                let verb = if off { "Unstarred" } else { "Starred" };
+                println!("{} {} items", verb, changed.len());
+            })
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,10 +1,66 @@
+use crate::placeholder::Placeholder;
+use crate::shuffle::{self, ShuffleWeights};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Folder {
     pub id: i64,
     pub path: String,
+    #[serde(flatten)]
+    pub settings: FolderSettings,
+    pub last_scanned_at: Option<i64>,
+}
+
+/// How a library folder is scanned, and whether its items show up at all.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FolderSettings {
+    pub is_active: bool, // Inactive folders keep their rows but are hidden everywhere
+    pub recursive: bool,
+    pub max_depth: Option<u32>, // Subfolder levels below the root; None for no limit
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    pub rescan_interval_min: Option<i64>, // None to only rescan by hand
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>, // gitignore-style, relative to the folder
+}
+
+impl Default for FolderSettings {
+    fn default() -> Self {
+        Self {
+            is_active: true,
+            recursive: true,
+            max_depth: None,
+            include_hidden: false,
+            follow_symlinks: false,
+            rescan_interval_min: None,
+            exclude_patterns: Vec::new(),
+        }
+    }
+}
+
+impl FolderSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rescan_interval_min.is_some_and(|m| m < 1) {
+            return Err("The rescan interval must be at least a minute".to_string());
+        }
+        crate::exclude::validate(&self.exclude_patterns)
+    }
+}
+
+/// A library folder or one of its subfolders, for the sidebar tree.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FolderNode {
+    pub folder_id: i64,
+    pub directory_id: Option<i64>, // None for a root nothing has been indexed under yet
+    pub path: String,
+    pub name: String,
     pub is_active: bool,
+    pub item_count: i64,  // Directly inside this folder
+    pub total_count: i64, // Including subfolders
+    pub children: Vec<FolderNode>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -16,8 +72,19 @@ pub struct MediaItem {
     pub created_at: i64,
     pub width: Option<i32>,
     pub height: Option<i32>,
-    pub duration_sec: Option<f64>,
+    pub duration_sec: Option<f64>, // Videos, and the total play time of animated images
     pub starred: bool,
+    pub is_animated: bool,
+    pub frame_count: Option<i64>,
+    pub loop_count: Option<i64>, // Times an animation plays; 0 loops forever
+    pub blurhash: Option<String>,
+    pub dominant_color: Option<String>, // "#rrggbb"
+    pub average_color: Option<String>,
+    pub colorfulness: Option<f64>, // Mean CIELAB chroma; near 0 for grayscale
+    pub view_count: i64,
+    pub last_viewed_at: Option<i64>,
+    pub playback_position: Option<f64>, // Seconds to resume a long video from
+    pub completed: bool,                // Watched to the end at least once
 }
 
 pub const SCHEMA_MEDIA: &str = "
@@ -50,6 +117,15 @@ pub struct Feed {
     pub filter_config: String, // JSON: FilterOptions
 }
 
+impl Feed {
+    /// The feed's stored filters, scoped to its folders (mirrors what the frontend sends to `get_media`).
+    pub fn filters(&self) -> Result<FilterOptions, serde_json::Error> {
+        let mut filters: FilterOptions = serde_json::from_str(&self.filter_config)?;
+        filters.folder_paths = Some(serde_json::from_str(&self.folder_paths)?);
+        Ok(filters)
+    }
+}
+
 pub const SCHEMA_FEEDS: &str = "
 CREATE TABLE IF NOT EXISTS feeds (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -65,9 +141,248 @@ CREATE INDEX IF NOT EXISTS idx_media_starred ON media_items(starred);
 CREATE INDEX IF NOT EXISTS idx_media_type ON media_items(file_type);
 ";
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Schema changes on top of the base tables, applied in order and tracked with `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    // 1: Recoverable trash and app settings
+    "
+    ALTER TABLE media_items ADD COLUMN trashed_at INTEGER;
+    ALTER TABLE media_items ADD COLUMN trash_path TEXT;
+    CREATE INDEX IF NOT EXISTS idx_media_trashed ON media_items(trashed_at);
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    ",
+    // 2: Content fingerprints for finding moved files
+    "
+    ALTER TABLE media_items ADD COLUMN quick_hash TEXT;
+    CREATE INDEX IF NOT EXISTS idx_media_hash ON media_items(quick_hash);
+    ",
+    // 3: Animated images; frame_count stays NULL until an image has been probed
+    "
+    ALTER TABLE media_items ADD COLUMN is_animated INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE media_items ADD COLUMN frame_count INTEGER;
+    ALTER TABLE media_items ADD COLUMN loop_count INTEGER;
+    ",
+    // 4: Loading placeholders
+    "
+    ALTER TABLE media_items ADD COLUMN blurhash TEXT;
+    ALTER TABLE media_items ADD COLUMN dominant_color TEXT;
+    ALTER TABLE media_items ADD COLUMN average_color TEXT;
+    ",
+    // 5: Perceptual color of the dominant swatch, for color search and hue sorting.
+    // Clearing the blurhash makes the backfill recompute placeholders with these included.
+    "
+    ALTER TABLE media_items ADD COLUMN color_l REAL;
+    ALTER TABLE media_items ADD COLUMN color_a REAL;
+    ALTER TABLE media_items ADD COLUMN color_b REAL;
+    ALTER TABLE media_items ADD COLUMN color_hue REAL;
+    ALTER TABLE media_items ADD COLUMN color_chroma REAL;
+    ALTER TABLE media_items ADD COLUMN colorfulness REAL;
+    UPDATE media_items SET blurhash = NULL;
+    ",
+    // 6: Items belong to a root folder and keep a path relative to it, so a root can be
+    // relocated in one step. `path` stays as the resolved absolute path; the triggers keep
+    // folder_id and rel_path in step with it (innermost root wins for nested roots).
+    "
+    ALTER TABLE media_items ADD COLUMN folder_id INTEGER REFERENCES folders(id) ON DELETE SET NULL;
+    ALTER TABLE media_items ADD COLUMN rel_path TEXT;
+    CREATE INDEX IF NOT EXISTS idx_media_folder ON media_items(folder_id);
+
+    CREATE TRIGGER IF NOT EXISTS media_folder_insert AFTER INSERT ON media_items BEGIN
+        UPDATE media_items SET folder_id = (
+            SELECT f.id FROM folders f
+            WHERE substr(NEW.path, 1, length(rtrim(f.path, '/')) + 1) = rtrim(f.path, '/') || '/'
+            ORDER BY length(f.path) DESC LIMIT 1
+        ) WHERE id = NEW.id;
+        UPDATE media_items SET rel_path = (
+            SELECT substr(NEW.path, length(rtrim(f.path, '/')) + 2) FROM folders f WHERE f.id = media_items.folder_id
+        ) WHERE id = NEW.id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS media_folder_update AFTER UPDATE OF path ON media_items BEGIN
+        UPDATE media_items SET folder_id = (
+            SELECT f.id FROM folders f
+            WHERE substr(NEW.path, 1, length(rtrim(f.path, '/')) + 1) = rtrim(f.path, '/') || '/'
+            ORDER BY length(f.path) DESC LIMIT 1
+        ) WHERE id = NEW.id;
+        UPDATE media_items SET rel_path = (
+            SELECT substr(NEW.path, length(rtrim(f.path, '/')) + 2) FROM folders f WHERE f.id = media_items.folder_id
+        ) WHERE id = NEW.id;
+    END;
+
+    -- Fires the update trigger for existing rows
+    UPDATE media_items SET path = path;
+    ",
+    // 7: Subfolders of each root, linked by parent. Deleting a root removes its subfolders
+    // and, through them, its items. Directories are created on demand from item paths; the
+    // parent trigger walks up to the root (rel_path '') and needs recursive triggers.
+    // `rtrim(p, replace(p, '/', ''))` keeps `p` up to its last '/'.
+    "
+    CREATE TABLE IF NOT EXISTS directories (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        folder_id INTEGER NOT NULL REFERENCES folders(id) ON DELETE CASCADE,
+        parent_id INTEGER REFERENCES directories(id) ON DELETE CASCADE,
+        rel_path TEXT NOT NULL,
+        UNIQUE (folder_id, rel_path)
+    );
+    CREATE INDEX IF NOT EXISTS idx_directories_parent ON directories(parent_id);
+    ALTER TABLE media_items ADD COLUMN directory_id INTEGER REFERENCES directories(id) ON DELETE CASCADE;
+    CREATE INDEX IF NOT EXISTS idx_media_directory ON media_items(directory_id);
+
+    CREATE TRIGGER IF NOT EXISTS directory_parent AFTER INSERT ON directories WHEN NEW.rel_path != '' BEGIN
+        INSERT OR IGNORE INTO directories (folder_id, rel_path)
+        VALUES (NEW.folder_id, rtrim(rtrim(NEW.rel_path, replace(NEW.rel_path, '/', '')), '/'));
+        UPDATE directories SET parent_id = (
+            SELECT p.id FROM directories p
+            WHERE p.folder_id = NEW.folder_id
+              AND p.rel_path = rtrim(rtrim(NEW.rel_path, replace(NEW.rel_path, '/', '')), '/')
+        ) WHERE id = NEW.id;
+    END;
+
+    -- rel_path is set last by the folder triggers, so this runs after them
+    CREATE TRIGGER IF NOT EXISTS media_directory AFTER UPDATE OF rel_path ON media_items BEGIN
+        INSERT OR IGNORE INTO directories (folder_id, rel_path)
+        SELECT NEW.folder_id, rtrim(rtrim(NEW.rel_path, replace(NEW.rel_path, '/', '')), '/')
+        WHERE NEW.rel_path IS NOT NULL;
+        UPDATE media_items SET directory_id = (
+            SELECT d.id FROM directories d
+            WHERE d.folder_id = NEW.folder_id
+              AND d.rel_path = rtrim(rtrim(NEW.rel_path, replace(NEW.rel_path, '/', '')), '/')
+        ) WHERE id = NEW.id;
+    END;
+
+    UPDATE media_items SET rel_path = rel_path;
+    ",
+    // 8: Per-folder scan settings (is_active is part of the base table)
+    "
+    ALTER TABLE folders ADD COLUMN recursive INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE folders ADD COLUMN max_depth INTEGER;
+    ALTER TABLE folders ADD COLUMN include_hidden INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE folders ADD COLUMN follow_symlinks INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE folders ADD COLUMN rescan_interval_min INTEGER;
+    ALTER TABLE folders ADD COLUMN last_scanned_at INTEGER;
+    ",
+    // 9: Per-folder exclude globs (JSON array)
+    "
+    ALTER TABLE folders ADD COLUMN exclude_patterns TEXT NOT NULL DEFAULT '[]';
+    ",
+    // 10: View history; a row once an item has been viewed
+    "
+    CREATE TABLE IF NOT EXISTS media_views (
+        media_id INTEGER PRIMARY KEY REFERENCES media_items(id) ON DELETE CASCADE,
+        first_viewed_at INTEGER NOT NULL,
+        last_viewed_at INTEGER NOT NULL,
+        view_count INTEGER NOT NULL DEFAULT 0,
+        watch_time_sec REAL NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS idx_views_last ON media_views(last_viewed_at);
+    ",
+    // 11: Where long videos were left off
+    "
+    CREATE TABLE IF NOT EXISTS media_playback (
+        media_id INTEGER PRIMARY KEY REFERENCES media_items(id) ON DELETE CASCADE,
+        position_sec REAL NOT NULL DEFAULT 0,
+        completed INTEGER NOT NULL DEFAULT 0,
+        played_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_playback_played ON media_playback(played_at);
+    ",
+    // 12: When the scanner first indexed an item; unknown for rows from before
+    "
+    ALTER TABLE media_items ADD COLUMN added_at INTEGER;
+    ",
+    // 13: Scans stored paths below the root with backslashes on Windows, which the folder
+    // triggers never matched. A row already stored under the normalized path takes over the
+    // star and the duplicate goes; normalizing the rest fires the triggers.
+    "
+    UPDATE media_items SET starred = 1 WHERE starred = 0 AND path IN (
+        SELECT replace(path, '\\', '/') FROM media_items WHERE starred = 1 AND instr(path, '\\') > 0
+    );
+    DELETE FROM media_items
+    WHERE instr(path, '\\') > 0 AND replace(path, '\\', '/') IN (SELECT path FROM media_items);
+    UPDATE media_items SET path = replace(path, '\\', '/') WHERE instr(path, '\\') > 0;
+    ",
+    // 14: Every view with its time, so a shuffle can weigh items as they were when it began.
+    // Earlier history only kept the first and the last view.
+    "
+    CREATE TABLE IF NOT EXISTS media_view_log (
+        media_id INTEGER NOT NULL REFERENCES media_items(id) ON DELETE CASCADE,
+        viewed_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_view_log ON media_view_log(media_id, viewed_at);
+    INSERT INTO media_view_log (media_id, viewed_at) SELECT media_id, first_viewed_at FROM media_views;
+    INSERT INTO media_view_log (media_id, viewed_at)
+    SELECT media_id, last_viewed_at FROM media_views WHERE last_viewed_at != first_viewed_at;
+    ",
+    // 15: File modification time as of the last scan, so rescans can skip unchanged files.
+    // Rows scanned before this are probed once more to fill it in.
+    "
+    ALTER TABLE media_items ADD COLUMN modified_at INTEGER;
+    ",
+];
+
+/// Opens a library database. Foreign keys and recursive triggers are per-connection
+/// settings that SQLite leaves off, and the schema relies on both. Queries may also use
+/// the `shuffle_key` function.
+pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA recursive_triggers = ON;")?;
+    shuffle::register(&conn)?;
+    Ok(conn)
+}
+
+/// Items of inactive folders stay indexed but are left out of every listing.
+pub const IN_ACTIVE_FOLDER: &str =
+    "(folder_id IS NULL OR folder_id IN (SELECT id FROM folders WHERE is_active = 1))";
+
+/// Absolute path of a `directories` row `d` in folder `f`.
+const DIRECTORY_PATH: &str =
+    "(CASE d.rel_path WHEN '' THEN rtrim(f.path, '/') ELSE rtrim(f.path, '/') || '/' || d.rel_path END)";
+
+pub fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(SCHEMA_MEDIA)?;
+    conn.execute_batch(SCHEMA_FOLDERS)?;
+    conn.execute_batch(SCHEMA_FEEDS)?;
+    conn.execute_batch(SCHEMA_INDICES)?;
+
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(migration)?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", i + 1))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LibrarySummary {
+    pub items: i64,
+    pub images: i64,
+    pub videos: i64,
+    pub starred: i64,
+    pub total_bytes: i64,
+    pub folders: i64,
+    pub feeds: i64,
+}
+
+/// Longest day range a filter accepts, about a century
+pub const MAX_DAYS: i64 = 36_500;
+pub const DEFAULT_COLOR_TOLERANCE: f64 = 20.0;
+/// Chroma below which a color reads as gray
+pub const GRAY_CHROMA: f64 = 8.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FilterOptions {
-    pub media_type: Option<String>,  // "image", "video", or "all"
+    pub media_type: Option<String>,  // "image", "video", "animated", or "all"
     pub orientation: Option<String>, // "horizontal", "vertical", "square", or "all"
     pub min_width: Option<i32>,
     pub min_height: Option<i32>,
@@ -78,13 +393,177 @@ pub struct FilterOptions {
     pub extensions: Option<Vec<String>>,
     pub folder_paths: Option<Vec<String>>, // Added for feed-specific logic
     pub favorites_only: Option<bool>,
-    pub sort_by: Option<String>, // "created_at", "size_bytes", "resolution", "duration_sec", "random"
+    pub color: Option<String>,        // "#rrggbb"; matched against each item's dominant color
+    pub color_tolerance: Option<f64>, // CIELAB ΔE; defaults to DEFAULT_COLOR_TOLERANCE
+    pub monochrome: Option<bool>,     // true: grayscale only, false: color only
+    pub unseen: Option<bool>,         // true: never viewed, false: viewed at least once
+    pub not_viewed_days: Option<i64>, // Leaves out items viewed within this many days
+    pub in_progress: Option<bool>,    // true: videos left off partway, false: everything else
+    pub sort_by: Option<String>, // "created_at", "size_bytes", "resolution", "duration_sec", "hue", "view_count", "last_viewed", "last_played", "random", "weighted"
     pub sort_order: Option<String>, // "asc", "desc"
+    pub shuffle_seed: Option<i64>,  // Same seed, same order for "random" and "weighted", so pages line up
+    pub shuffle_weights: Option<ShuffleWeights>, // For "weighted"; defaults when unset
+    pub shuffle_as_of: Option<i64>, // Unix time "weighted" weighs items as of; later views don't count
+}
+
+impl FilterOptions {
+    /// Rejects values `get_media` would misread or splice into SQL unchecked.
+    /// Needed for filters that didn't come from our own UI (e.g. imported feeds).
+    pub fn validate(&self) -> Result<(), String> {
+        fn one_of(field: &str, value: &Option<String>, allowed: &[&str]) -> Result<(), String> {
+            match value {
+                Some(v) if !allowed.contains(&v.as_str()) => {
+                    Err(format!("{} must be one of {}, not \"{}\"", field, allowed.join(", "), v))
+                }
+                _ => Ok(()),
+            }
+        }
+        one_of("media_type", &self.media_type, &["image", "video", "animated", "all"])?;
+        one_of("orientation", &self.orientation, &["horizontal", "vertical", "square", "all"])?;
+        one_of(
+            "sort_by",
+            &self.sort_by,
+            &[
+                "created_at",
+                "size_bytes",
+                "resolution",
+                "duration_sec",
+                "filename",
+                "hue",
+                "view_count",
+                "last_viewed",
+                "last_played",
+                "random",
+                "weighted",
+            ],
+        )?;
+        one_of("sort_order", &self.sort_order, &["asc", "desc"])?;
+
+        let negative = [
+            self.min_width.map(i64::from),
+            self.min_height.map(i64::from),
+            self.min_size,
+            self.max_size,
+            self.not_viewed_days,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|v| v < 0)
+            || [self.min_duration, self.max_duration]
+                .into_iter()
+                .flatten()
+                .any(|v| !v.is_finite() || v < 0.0);
+        if negative {
+            return Err("Size, dimension, duration and day limits can't be negative".to_string());
+        }
+        if self.not_viewed_days.is_some_and(|days| days > MAX_DAYS) {
+            return Err(format!("not_viewed_days can be at most {}", MAX_DAYS));
+        }
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min > max {
+                return Err("min_size is larger than max_size".to_string());
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if max > 0.0 && min > max {
+                return Err("min_duration is longer than max_duration".to_string());
+            }
+        }
+        if let Some(weights) = &self.shuffle_weights {
+            weights.validate()?;
+        }
+        if self.shuffle_as_of.is_some_and(|t| t < 0) {
+            return Err("shuffle_as_of can't be negative".to_string());
+        }
+        if let Some(color) = &self.color {
+            if parse_hex_color(color).is_none() {
+                return Err(format!("\"{}\" is not a #rrggbb color", color));
+            }
+        }
+        if self.color_tolerance.is_some_and(|t| !t.is_finite() || !(0.0..=100.0).contains(&t)) {
+            return Err("color_tolerance must be between 0 and 100".to_string());
+        }
+        for ext in self.extensions.iter().flatten() {
+            if ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(format!("\"{}\" is not a file extension", ext));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// "#rrggbb" to RGB.
+pub fn parse_hex_color(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.strip_prefix('#')?;
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// sRGB (D65) to CIELAB.
+pub fn srgb_to_lab(rgb: [u8; 3]) -> [f64; 3] {
+    let linear = rgb.map(|c| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let [r, g, b] = linear;
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
 }
 
 pub mod changes {
     use super::*;
-    use rusqlite::{params, Connection, Result};
+    use rusqlite::types::Value;
+    use rusqlite::{params, params_from_iter, Connection, Result, Row};
+
+    const MEDIA_COLUMNS: &str =
+        "id, path, file_type, size_bytes, created_at, width, height, duration_sec, starred, is_animated, frame_count, loop_count, NULLIF(blurhash, ''), dominant_color, average_color, colorfulness, COALESCE(view_count, 0), last_viewed_at, NULLIF(position_sec, 0), COALESCE(completed, 0)";
+
+    /// Media with their view history and playback state, if any.
+    const MEDIA_TABLES: &str = "media_items \
+        LEFT JOIN media_views ON media_views.media_id = media_items.id \
+        LEFT JOIN media_playback ON media_playback.media_id = media_items.id";
+
+    fn media_from_row(row: &Row) -> Result<MediaItem> {
+        Ok(MediaItem {
+            id: Some(row.get(0)?),
+            path: row.get(1)?,
+            file_type: row.get(2)?,
+            size_bytes: row.get(3)?,
+            created_at: row.get(4)?,
+            width: row.get(5).ok(),
+            height: row.get(6).ok(),
+            duration_sec: row.get(7).ok(),
+            starred: row.get(8)?,
+            is_animated: row.get(9)?,
+            frame_count: row.get(10)?,
+            loop_count: row.get(11)?,
+            blurhash: row.get(12)?,
+            dominant_color: row.get(13)?,
+            average_color: row.get(14)?,
+            colorfulness: row.get(15)?,
+            view_count: row.get(16)?,
+            last_viewed_at: row.get(17)?,
+            playback_position: row.get(18)?,
+            completed: row.get(19)?,
+        })
+    }
 
     pub fn get_feeds(conn: &Connection) -> Result<Vec<Feed>> {
         let mut stmt = conn.prepare("SELECT id, name, folder_paths, filter_config FROM feeds")?;
@@ -117,19 +596,43 @@ pub mod changes {
         Ok(())
     }
 
+    pub fn get_feed(conn: &Connection, id: i64) -> Result<Feed> {
+        conn.query_row(
+            "SELECT id, name, folder_paths, filter_config FROM feeds WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Feed {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    folder_paths: row.get(2)?,
+                    filter_config: row.get(3)?,
+                })
+            },
+        )
+    }
+
     pub fn delete_feed(conn: &Connection, id: i64) -> Result<()> {
         conn.execute("DELETE FROM feeds WHERE id = ?1", params![id])?;
         Ok(())
     }
 
+    /// Adds `value` to the query's arguments and returns its placeholder.
+    fn bind(args: &mut Vec<Value>, value: impl Into<Value>) -> String {
+        args.push(value.into());
+        format!("?{}", args.len())
+    }
+
     pub fn get_media(
         conn: &Connection,
         limit: i64,
         offset: i64,
         filters: FilterOptions,
     ) -> Result<Vec<MediaItem>> {
-        let mut query = "SELECT id, path, file_type, size_bytes, created_at, width, height, duration_sec, starred FROM media_items".to_string();
-        let mut where_clauses = Vec::new();
+        let mut query = format!("SELECT {} FROM {}", MEDIA_COLUMNS, MEDIA_TABLES);
+        // Strings from the filters are always bound, never spliced into the SQL
+        let mut args: Vec<Value> = Vec::new();
+        // Trashed items stay in the table until the trash is emptied
+        let mut where_clauses = vec!["trashed_at IS NULL".to_string(), IN_ACTIVE_FOLDER.to_string()];
 
         if let Some(true) = filters.favorites_only {
             where_clauses.push("starred = 1".to_string());
@@ -138,19 +641,32 @@ pub mod changes {
         // Feed / Folder constraints
         if let Some(paths) = filters.folder_paths {
             if !paths.is_empty() {
-                let mut folder_likes = Vec::new();
+                // A root or any subfolder, matched by whole path segments
+                let mut folder_matches = Vec::new();
+                let mut path_matches = Vec::new();
                 for p in paths {
-                    // Escape single quotes for SQLite
-                    let escaped = p.replace("'", "''");
-                    folder_likes.push(format!("path LIKE '{}%'", escaped));
+                    let path = bind(&mut args, p.trim_end_matches('/').to_string());
+                    folder_matches.push(format!(
+                        "{0} = {1} OR substr({0}, 1, length({1}) + 1) = {1} || '/'",
+                        DIRECTORY_PATH, path
+                    ));
+                    path_matches.push(format!("substr(path, 1, length({0}) + 1) = {0} || '/'", path));
                 }
-                where_clauses.push(format!("({})", folder_likes.join(" OR ")));
+                // Items the triggers couldn't place under a root fall back to their path
+                where_clauses.push(format!(
+                    "(directory_id IN (SELECT d.id FROM directories d JOIN folders f ON f.id = d.folder_id WHERE {}) \
+                     OR (directory_id IS NULL AND ({})))",
+                    folder_matches.join(" OR "),
+                    path_matches.join(" OR ")
+                ));
             }
         }
 
         if let Some(mt) = filters.media_type {
-            if mt != "all" {
-                where_clauses.push(format!("file_type = '{}'", mt));
+            match mt.as_str() {
+                "all" => {}
+                "animated" => where_clauses.push("is_animated = 1".to_string()),
+                _ => where_clauses.push(format!("file_type = {}", bind(&mut args, mt))),
             }
         }
 
@@ -192,12 +708,46 @@ pub mod changes {
             where_clauses.push(format!("size_bytes <= {}", max_s));
         }
 
+        if let Some(lab) = filters.color.as_deref().and_then(parse_hex_color).map(srgb_to_lab) {
+            let tolerance = filters.color_tolerance.unwrap_or(DEFAULT_COLOR_TOLERANCE);
+            // CIE76 distance, squared to keep it to plain arithmetic
+            where_clauses.push(format!(
+                "((color_l - {l}) * (color_l - {l}) + (color_a - {a}) * (color_a - {a}) + (color_b - {b}) * (color_b - {b})) <= {t}",
+                l = lab[0],
+                a = lab[1],
+                b = lab[2],
+                t = tolerance * tolerance
+            ));
+        }
+        match filters.monochrome {
+            Some(true) => where_clauses.push(format!("colorfulness < {}", GRAY_CHROMA)),
+            Some(false) => where_clauses.push(format!("colorfulness >= {}", GRAY_CHROMA)),
+            None => {}
+        }
+
+        match filters.unseen {
+            Some(true) => where_clauses.push("view_count IS NULL".to_string()),
+            Some(false) => where_clauses.push("view_count IS NOT NULL".to_string()),
+            None => {}
+        }
+        if let Some(days) = filters.not_viewed_days {
+            where_clauses.push(format!(
+                "(last_viewed_at IS NULL OR last_viewed_at < {})",
+                unix_now().saturating_sub(days.saturating_mul(24 * 60 * 60))
+            ));
+        }
+        match filters.in_progress {
+            Some(true) => where_clauses.push("position_sec > 0".to_string()),
+            Some(false) => where_clauses.push("COALESCE(position_sec, 0) = 0".to_string()),
+            None => {}
+        }
+
         if let Some(exts) = filters.extensions {
             if !exts.is_empty() {
                 let mut ext_clauses = Vec::new();
                 for ext in exts {
-                    let escaped_ext = ext.to_lowercase().replace("'", "''");
-                    ext_clauses.push(format!("path LIKE '%.{}'", escaped_ext));
+                    let pattern = format!("%.{}", ext.to_lowercase());
+                    ext_clauses.push(format!("path LIKE {}", bind(&mut args, pattern)));
                 }
                 where_clauses.push(format!("({})", ext_clauses.join(" OR ")));
             }
@@ -214,7 +764,12 @@ pub mod changes {
             Some("resolution") => "(width * height)",
             Some("duration_sec") => "duration_sec",
             Some("filename") => "path",
+            Some("hue") => "color_hue",
+            Some("view_count") => "COALESCE(view_count, 0)",
+            Some("last_viewed") => "COALESCE(last_viewed_at, 0)",
+            Some("last_played") => "COALESCE(played_at, 0)",
             Some("random") => "RANDOM()",
+            Some("weighted") => "weighted",
             _ => "created_at",
         };
 
@@ -225,66 +780,240 @@ pub mod changes {
         };
 
         if sort_col == "RANDOM()" {
-            query.push_str(" ORDER BY RANDOM()");
+            match filters.shuffle_seed {
+                Some(seed) => query.push_str(&format!(" ORDER BY shuffle_key({}, id, 1), id", seed)),
+                None => query.push_str(" ORDER BY RANDOM()"),
+            }
+        } else if sort_col == "weighted" {
+            // Without a seed every row draws its own, which is just as random but can't be paged
+            let seed = filters.shuffle_seed.map_or("RANDOM()".to_string(), |s| s.to_string());
+            let weights = filters.shuffle_weights.unwrap_or_default();
+            // Fixed along with the seed, so views recorded while paging don't move items around
+            let as_of = filters.shuffle_as_of.unwrap_or_else(unix_now);
+            query.push_str(&format!(
+                " ORDER BY shuffle_key({}, id, {}) DESC, id",
+                seed,
+                weights.weight_sql(as_of)
+            ));
+        } else if sort_col == "color_hue" {
+            // Grays have no meaningful hue: they follow the colors, darkest to lightest
+            query.push_str(&format!(
+                " ORDER BY color_hue IS NULL, color_chroma < {gray}, CASE WHEN color_chroma < {gray} THEN color_l ELSE color_hue END {order}",
+                gray = GRAY_CHROMA,
+                order = order
+            ));
         } else {
             query.push_str(&format!(" ORDER BY {} {}", sort_col, order));
         }
 
-        query.push_str(" LIMIT ?1 OFFSET ?2");
+        let limit = bind(&mut args, limit);
+        let offset = bind(&mut args, offset);
+        query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
         let mut stmt = conn.prepare(&query)?;
         let items = stmt
-            .query_map(params![limit, offset], |row| {
-                Ok(MediaItem {
-                    id: Some(row.get(0)?),
-                    path: row.get(1)?,
-                    file_type: row.get(2)?,
-                    size_bytes: row.get(3)?,
-                    created_at: row.get(4)?,
-                    width: row.get(5).ok(),
-                    height: row.get(6).ok(),
-                    duration_sec: row.get(7).ok(),
-                    starred: row.get(8)?,
-                })
-            })?
+            .query_map(params_from_iter(args), media_from_row)?
             .filter_map(Result::ok)
             .collect();
         Ok(items)
     }
 
+    /// Items in the order of `ids`; unknown ids, trashed items and items of inactive folders
+    /// are left out, as in `get_media`.
+    pub fn get_media_by_ids(conn: &Connection, ids: &[i64]) -> Result<Vec<MediaItem>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM {} WHERE id = ?1 AND trashed_at IS NULL AND {}",
+            MEDIA_COLUMNS, MEDIA_TABLES, IN_ACTIVE_FOLDER
+        ))?;
+        let mut items = Vec::new();
+        for id in ids {
+            match stmt.query_row(params![id], media_from_row) {
+                Ok(item) => items.push(item),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(items)
+    }
+
+    const FOLDER_COLUMNS: &str = "id, path, is_active, recursive, max_depth, include_hidden, \
+        follow_symlinks, rescan_interval_min, last_scanned_at, exclude_patterns";
+
+    fn folder_from_row(row: &Row) -> Result<Folder> {
+        Ok(Folder {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            settings: FolderSettings {
+                is_active: row.get(2)?,
+                recursive: row.get(3)?,
+                max_depth: row.get(4)?,
+                include_hidden: row.get(5)?,
+                follow_symlinks: row.get(6)?,
+                rescan_interval_min: row.get(7)?,
+                exclude_patterns: serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default(),
+            },
+            last_scanned_at: row.get(8)?,
+        })
+    }
+
     pub fn get_folders(conn: &Connection) -> Result<Vec<Folder>> {
-        let mut stmt = conn.prepare("SELECT id, path, is_active FROM folders")?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM folders", FOLDER_COLUMNS))?;
         let folders = stmt
-            .query_map([], |row| {
-                Ok(Folder {
-                    id: row.get(0)?,
-                    path: row.get(1)?,
-                    is_active: row.get(2)?,
-                })
-            })?
+            .query_map([], folder_from_row)?
             .filter_map(Result::ok)
             .collect();
         Ok(folders)
     }
 
-    pub fn add_folder(conn: &Connection, path: &str) -> Result<()> {
+    pub fn get_folder(conn: &Connection, path: &str) -> Result<Option<Folder>> {
+        match conn.query_row(
+            &format!("SELECT {} FROM folders WHERE path = ?1", FOLDER_COLUMNS),
+            params![path],
+            folder_from_row,
+        ) {
+            Ok(folder) => Ok(Some(folder)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns false if `path` isn't a library folder.
+    pub fn set_folder_settings(conn: &Connection, path: &str, settings: &FolderSettings) -> Result<bool> {
+        let updated = conn.execute(
+            "UPDATE folders SET is_active = ?1, recursive = ?2, max_depth = ?3, include_hidden = ?4,
+                follow_symlinks = ?5, rescan_interval_min = ?6, exclude_patterns = ?7 WHERE path = ?8",
+            params![
+                settings.is_active,
+                settings.recursive,
+                settings.max_depth,
+                settings.include_hidden,
+                settings.follow_symlinks,
+                settings.rescan_interval_min,
+                serde_json::to_string(&settings.exclude_patterns).unwrap_or_else(|_| "[]".to_string()),
+                path
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn mark_folder_scanned(conn: &Connection, path: &str) -> Result<()> {
         conn.execute(
+            "UPDATE folders SET last_scanned_at = ?1 WHERE path = ?2",
+            params![unix_now(), path],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_folder(conn: &Connection, path: &str) -> Result<()> {
+        let added = conn.execute(
             "INSERT OR IGNORE INTO folders (path) VALUES (?1)",
             params![path],
         )?;
+        if added > 0 {
+            // Items already indexed under a new nested root move over to it
+            reassign_folder(conn, path)?;
+        }
         Ok(())
     }
 
-    pub fn remove_folder(conn: &Connection, path: &str) -> Result<()> {
-        conn.execute("DELETE FROM folders WHERE path = ?1", params![path])?;
-        let like_query = format!("{}\\%", path);
+    /// Re-runs root assignment for every item under `root`.
+    pub fn reassign_folder(conn: &Connection, root: &str) -> Result<()> {
+        let root = root.trim_end_matches('/');
         conn.execute(
-            "DELETE FROM media_items WHERE path LIKE ?1 OR path = ?2",
-            params![like_query, path],
+            "UPDATE media_items SET path = path WHERE substr(path, 1, length(?1) + 1) = ?1 || '/'",
+            params![root],
         )?;
         Ok(())
     }
 
+    /// Forgets a root; its subfolders and items go with it.
+    pub fn remove_folder(conn: &Connection, path: &str) -> Result<()> {
+        conn.execute("DELETE FROM folders WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    /// Every root with its subfolders nested below it. Subfolders that no longer hold
+    /// anything are left out.
+    pub fn get_folder_tree(conn: &Connection) -> Result<Vec<FolderNode>> {
+        let mut counts: HashMap<i64, i64> = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT directory_id, COUNT(*) FROM media_items
+             WHERE trashed_at IS NULL AND directory_id IS NOT NULL GROUP BY directory_id",
+        )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (id, count) = row?;
+            counts.insert(id, count);
+        }
+
+        // Root directory per folder, and subdirectories by parent
+        let mut roots: HashMap<i64, i64> = HashMap::new();
+        let mut children: HashMap<i64, Vec<(i64, String)>> = HashMap::new();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT d.id, d.folder_id, d.parent_id, {} FROM directories d
+             JOIN folders f ON f.id = d.folder_id ORDER BY d.rel_path",
+            DIRECTORY_PATH
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<i64>>(2)?, row.get(3)?))
+        })?;
+        for row in rows {
+            let (id, folder_id, parent_id, path) = row?;
+            match parent_id {
+                Some(parent_id) => children.entry(parent_id).or_default().push((id, path)),
+                None => {
+                    roots.insert(folder_id, id);
+                }
+            }
+        }
+
+        fn subtree(
+            folder: &Folder,
+            id: i64,
+            path: String,
+            children: &HashMap<i64, Vec<(i64, String)>>,
+            counts: &HashMap<i64, i64>,
+        ) -> FolderNode {
+            let item_count = counts.get(&id).copied().unwrap_or(0);
+            let mut node = FolderNode {
+                folder_id: folder.id,
+                directory_id: Some(id),
+                name: path.rsplit('/').next().unwrap_or_default().to_string(),
+                path,
+                is_active: folder.settings.is_active,
+                item_count,
+                total_count: item_count,
+                children: Vec::new(),
+            };
+            for (child_id, child_path) in children.get(&id).into_iter().flatten() {
+                let child = subtree(folder, *child_id, child_path.clone(), children, counts);
+                node.total_count += child.total_count;
+                if child.total_count > 0 {
+                    node.children.push(child);
+                }
+            }
+            node
+        }
+
+        let mut tree = Vec::new();
+        for folder in get_folders(conn)? {
+            let root = folder.path.trim_end_matches('/').to_string();
+            tree.push(match roots.get(&folder.id) {
+                Some(&id) => subtree(&folder, id, root, &children, &counts),
+                None => FolderNode {
+                    folder_id: folder.id,
+                    directory_id: None,
+                    name: root.rsplit('/').next().unwrap_or_default().to_string(),
+                    path: root,
+                    is_active: folder.settings.is_active,
+                    item_count: 0,
+                    total_count: 0,
+                    children: Vec::new(),
+                },
+            });
+        }
+        Ok(tree)
+    }
+
     pub fn toggle_star(conn: &Connection, id: i64) -> Result<bool> {
         let currently_starred: bool = conn
             .query_row(
@@ -302,13 +1031,33 @@ pub mod changes {
         Ok(new_status)
     }
 
-    pub fn get_starred_items(conn: &Connection) -> Result<Vec<String>> {
-        let mut stmt = conn.prepare("SELECT path FROM media_items WHERE starred = 1")?;
-        let paths = stmt
-            .query_map([], |row| row.get(0))?
-            .filter_map(Result::ok)
-            .collect();
-        Ok(paths)
+    pub fn update_media_placeholder(conn: &Connection, id: i64, placeholder: &Placeholder) -> Result<()> {
+        let [l, a, b] = placeholder.dominant_lab;
+        conn.execute(
+            "UPDATE media_items SET blurhash = ?1, dominant_color = ?2, average_color = ?3,
+                 color_l = ?4, color_a = ?5, color_b = ?6, color_hue = ?7, color_chroma = ?8, colorfulness = ?9
+             WHERE id = ?10",
+            params![
+                placeholder.blurhash,
+                placeholder.dominant_color,
+                placeholder.average_color,
+                l,
+                a,
+                b,
+                b.atan2(a).to_degrees().rem_euclid(360.0),
+                a.hypot(b),
+                placeholder.colorfulness,
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records that no placeholder can be made for `id`, so the backfill doesn't keep retrying
+    /// it. The empty blurhash reads back as none.
+    pub fn mark_placeholder_failed(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("UPDATE media_items SET blurhash = '' WHERE id = ?1", params![id])?;
+        Ok(())
     }
 
     pub fn update_media_dimensions(
@@ -324,6 +1073,65 @@ pub mod changes {
         Ok(())
     }
 
+    /// Returns false when no item has that id.
+    pub fn set_starred(conn: &Connection, id: i64, starred: bool) -> Result<bool> {
+        let changed = conn.execute(
+            "UPDATE media_items SET starred = ?1 WHERE id = ?2",
+            params![starred, id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Item counts cover what the feeds show: trashed items and inactive folders are left out.
+    pub fn get_library_summary(conn: &Connection) -> Result<LibrarySummary> {
+        let mut summary = conn.query_row(
+            &format!(
+                "SELECT COUNT(*),
+                        COALESCE(SUM(file_type = 'image'), 0),
+                        COALESCE(SUM(file_type = 'video'), 0),
+                        COALESCE(SUM(starred), 0),
+                        COALESCE(SUM(size_bytes), 0)
+                 FROM media_items WHERE trashed_at IS NULL AND {}",
+                IN_ACTIVE_FOLDER
+            ),
+            [],
+            |row| {
+                Ok(LibrarySummary {
+                    items: row.get(0)?,
+                    images: row.get(1)?,
+                    videos: row.get(2)?,
+                    starred: row.get(3)?,
+                    total_bytes: row.get(4)?,
+                    ..Default::default()
+                })
+            },
+        )?;
+        summary.folders = conn.query_row("SELECT COUNT(*) FROM folders", [], |row| row.get(0))?;
+        summary.feeds = conn.query_row("SELECT COUNT(*) FROM feeds", [], |row| row.get(0))?;
+        Ok(summary)
+    }
+
+    pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+        match conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        ) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
     pub fn clear_favorites(conn: &Connection) -> Result<()> {
         conn.execute("UPDATE media_items SET starred = 0", [])?;
         Ok(())
@@ -0,0 +1,212 @@
+use crate::db::{self, changes};
+use glob::{MatchOptions, Pattern};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-directory rules file, gitignore syntax
+pub const IGNORE_FILE: &str = ".xcrollerignore";
+
+const GLOBAL_PATTERNS_KEY: &str = "exclude_patterns";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: !cfg!(windows),
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    negate: bool,
+    dir_only: bool,
+    /// Matched against the whole path below the rule's directory instead of just the name
+    anchored: bool,
+}
+
+impl Rule {
+    /// One gitignore line; `None` for blanks and comments.
+    fn parse(line: &str) -> Option<Result<Rule, String>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(
+            Pattern::new(line)
+                .map(|pattern| Rule {
+                    pattern,
+                    negate,
+                    dir_only,
+                    anchored,
+                })
+                .map_err(|e| format!("Bad exclude pattern \"{}\": {}", line, e)),
+        )
+    }
+
+    fn matches(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let subject = if self.anchored {
+            rel_path
+        } else {
+            rel_path.rsplit('/').next().unwrap_or(rel_path)
+        };
+        self.pattern.matches_with(subject, MATCH_OPTIONS)
+    }
+}
+
+fn parse_all<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Rule> {
+    lines
+        .into_iter()
+        .filter_map(Rule::parse)
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Checks patterns before they're stored, so a typo is reported instead of silently ignored.
+pub fn validate(patterns: &[String]) -> Result<(), String> {
+    for pattern in patterns {
+        if let Some(Err(e)) = Rule::parse(pattern) {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+pub fn global_patterns(conn: &Connection) -> Vec<String> {
+    changes::get_setting(conn, GLOBAL_PATTERNS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_global_patterns(conn: &Connection, patterns: &[String]) -> Result<(), String> {
+    validate(patterns)?;
+    let json = serde_json::to_string(patterns).map_err(|e| e.to_string())?;
+    changes::set_setting(conn, GLOBAL_PATTERNS_KEY, &json).map_err(|e| e.to_string())
+}
+
+/// Decides what is left out below one library root. Global and folder patterns act like an
+/// ignore file at the root; `.xcrollerignore` files apply to their own directory and below,
+/// deeper files taking precedence. As in git, the last matching rule wins.
+pub struct Excluder {
+    root: PathBuf,
+    base: Vec<Rule>,
+    /// Rules from each directory's ignore file, by path relative to the root ("" for the root)
+    files: HashMap<String, Vec<Rule>>,
+}
+
+impl Excluder {
+    pub fn new(root: &Path, patterns: &[String]) -> Self {
+        Excluder {
+            root: root.to_path_buf(),
+            base: parse_all(patterns.iter().map(String::as_str)),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Global patterns plus the folder's own.
+    pub fn for_folder(conn: &Connection, root: &str, folder_patterns: &[String]) -> Self {
+        let mut patterns = global_patterns(conn);
+        patterns.extend_from_slice(folder_patterns);
+        Self::new(Path::new(root), &patterns)
+    }
+
+    fn file_rules(&mut self, dir: &str) -> &[Rule] {
+        let root = &self.root;
+        self.files.entry(dir.to_string()).or_insert_with(|| {
+            let path = if dir.is_empty() { root.join(IGNORE_FILE) } else { root.join(dir).join(IGNORE_FILE) };
+            fs::read_to_string(path)
+                .map(|text| parse_all(text.lines()))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Whether `rel_path` itself is excluded, assuming its parent directories are not.
+    /// That's the walker's view: excluded directories are never entered.
+    pub fn is_excluded(&mut self, rel_path: &str, is_dir: bool) -> bool {
+        let mut excluded = self
+            .base
+            .iter()
+            .rev()
+            .find(|r| r.matches(rel_path, is_dir))
+            .is_some_and(|r| !r.negate);
+
+        // Ignore files from the root down to the parent directory
+        let dir_ends = std::iter::once(0).chain(rel_path.match_indices('/').map(|(i, _)| i));
+        for end in dir_ends {
+            let (dir, below) = if end == 0 {
+                ("", rel_path)
+            } else {
+                (&rel_path[..end], &rel_path[end + 1..])
+            };
+            if let Some(rule) = self.file_rules(dir).iter().rev().find(|r| r.matches(below, is_dir)) {
+                excluded = !rule.negate;
+            }
+        }
+        excluded
+    }
+
+    /// Whether `rel_path` (a file) or any directory above it is excluded.
+    pub fn is_excluded_with_parents(&mut self, rel_path: &str) -> bool {
+        for (i, _) in rel_path.match_indices('/') {
+            if self.is_excluded(&rel_path[..i], true) {
+                return true;
+            }
+        }
+        self.is_excluded(rel_path, false)
+    }
+}
+
+/// Drops rows under `folder` that its rules now exclude. Trashed items are kept so they can
+/// still be restored.
+pub fn prune_folder(conn: &Connection, folder: &db::Folder) -> Result<usize, String> {
+    let mut excluder = Excluder::for_folder(conn, &folder.path, &folder.settings.exclude_patterns);
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, rel_path FROM media_items WHERE folder_id = ?1 AND rel_path IS NOT NULL AND trashed_at IS NULL")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![folder.id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+        rows
+    };
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut pruned = 0;
+    for (id, rel_path) in rows {
+        if excluder.is_excluded_with_parents(&rel_path) {
+            pruned += tx
+                .execute("DELETE FROM media_items WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(pruned)
+}
+
+pub fn prune_all(conn: &Connection) -> Result<usize, String> {
+    let mut pruned = 0;
+    for folder in changes::get_folders(conn).map_err(|e| e.to_string())? {
+        pruned += prune_folder(conn, &folder)?;
+    }
+    Ok(pruned)
+}
@@ -0,0 +1,644 @@
+use crate::db::{self, FilterOptions, MediaItem};
+use crate::{fsops, health, normalize_path};
+use crate::transform::ImageTransform;
+use rayon::prelude::*;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const MANIFEST_NAME: &str = "xcroller-manifest";
+
+/// What to do when an exported file name is already taken in the target folder.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    Skip,
+    Overwrite,
+    #[default]
+    Rename, // "IMG_0001 (1).jpg"
+    PrefixFolder, // "Holiday_IMG_0001.jpg"
+}
+
+/// How exported files get to the target.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportMode {
+    #[default]
+    Copy,
+    Move, // Also repoints `media_items.path` at the new location
+    Hardlink,
+    Symlink,
+    Zip, // The target is the archive file rather than a folder
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestFormat {
+    Json,
+    Csv,
+}
+
+/// Which items an export covers.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportSource {
+    Filters(Box<FilterOptions>),
+    Feed(i64),
+    Favorites,
+    Ids(Vec<i64>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub mode: ExportMode,
+    #[serde(default)]
+    pub collision: CollisionPolicy,
+    // Recreate each item's folders below its library root instead of copying flat
+    #[serde(default)]
+    pub preserve_structure: bool,
+    pub manifest: Option<ManifestFormat>,
+    // Applied to still images only; needs the copy or ZIP mode
+    pub transform: Option<ImageTransform>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStatus {
+    Exported,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportFileResult {
+    pub id: Option<i64>,
+    pub source: String,
+    pub destination: Option<String>,
+    pub status: ExportStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ExportReport {
+    pub exported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub files: Vec<ExportFileResult>,
+    pub manifest_path: Option<String>, // For ZIP exports the manifest is stored inside the archive
+    /// Moved files that now lie outside every library folder (move exports only)
+    pub left_library: usize,
+}
+
+impl ExportReport {
+    fn from_results(files: Vec<ExportFileResult>) -> Self {
+        let count = |s: ExportStatus| files.iter().filter(|f| f.status == s).count();
+        ExportReport {
+            exported: count(ExportStatus::Exported),
+            skipped: count(ExportStatus::Skipped),
+            failed: count(ExportStatus::Failed),
+            files,
+            manifest_path: None,
+            left_library: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportProgress {
+    pub done: usize,
+    pub total: usize,
+    pub current: String,
+}
+
+enum Planned {
+    Write(PathBuf),
+    Skip(String),
+}
+
+/// Resolves `source` to the items it covers, in the order `get_media` would return them.
+pub fn select_items(conn: &Connection, source: &ExportSource) -> Result<Vec<MediaItem>, String> {
+    let filters = match source {
+        ExportSource::Ids(ids) => {
+            return db::changes::get_media_by_ids(conn, ids).map_err(|e| e.to_string())
+        }
+        ExportSource::Filters(filters) => filters.as_ref().clone(),
+        ExportSource::Feed(id) => db::changes::get_feed(conn, *id)
+            .map_err(|e| e.to_string())?
+            .filters()
+            .map_err(|e| e.to_string())?,
+        ExportSource::Favorites => FilterOptions {
+            favorites_only: Some(true),
+            ..Default::default()
+        },
+    };
+    // A negative LIMIT means "no limit" in SQLite
+    db::changes::get_media(conn, -1, 0, filters).map_err(|e| e.to_string())
+}
+
+/// Checks that `options.mode` can work for this target before any file is touched.
+pub fn verify_export(items: &[MediaItem], target: &Path, options: &ExportOptions) -> Result<(), String> {
+    if let Some(transform) = &options.transform {
+        transform.validate()?;
+        if !matches!(options.mode, ExportMode::Copy | ExportMode::Zip) {
+            return Err("Image transforms are only available for copy and ZIP exports".to_string());
+        }
+    }
+
+    if options.mode == ExportMode::Zip {
+        if target.is_dir() {
+            return Err(format!("{} is a folder; ZIP exports need an archive file path", target.display()));
+        }
+        if target.exists() && options.collision != CollisionPolicy::Overwrite {
+            return Err(format!("{} already exists", target.display()));
+        }
+        return match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+                Err(format!("{} does not exist", parent.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    fs::create_dir_all(target).map_err(|e| e.to_string())?;
+    let probe = target.join(".xcroller-probe");
+    fs::write(&probe, b"").map_err(|e| format!("{} is not writable: {}", target.display(), e))?;
+    let _ = fs::remove_file(&probe);
+
+    let Some(sample) = items.iter().map(|i| Path::new(&i.path)).find(|p| p.is_file()) else {
+        return Ok(());
+    };
+
+    match options.mode {
+        ExportMode::Hardlink => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let target_dev = fs::metadata(target).map_err(|e| e.to_string())?.dev();
+                let foreign = items
+                    .iter()
+                    .filter_map(|i| fs::metadata(&i.path).ok())
+                    .filter(|m| m.dev() != target_dev)
+                    .count();
+                if foreign > 0 {
+                    return Err(format!(
+                        "{} items are on a different filesystem than {}; hardlinks can't cross filesystems",
+                        foreign,
+                        target.display()
+                    ));
+                }
+            }
+            fs::hard_link(sample, &probe)
+                .map_err(|e| format!("Hardlinks are not supported here: {}", e))?;
+            let _ = fs::remove_file(&probe);
+        }
+        ExportMode::Symlink => {
+            fsops::symlink_file(sample, &probe)
+                .map_err(|e| format!("Symlinks are not supported here: {}", e))?;
+            let _ = fs::remove_file(&probe);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Exports `items` to `target` with `options.mode`, resolving name collisions with `options.collision`.
+/// `roots` are the registered library folders, used when `preserve_structure` is set.
+pub fn export_items(
+    items: &[MediaItem],
+    roots: &[String],
+    target: &Path,
+    options: &ExportOptions,
+    progress: &(dyn Fn(ExportProgress) + Sync),
+) -> Result<ExportReport, String> {
+    verify_export(items, target, options)?;
+
+    if options.mode == ExportMode::Zip {
+        return export_zip(items, roots, target, options, progress);
+    }
+
+    // Destinations are planned up front so parallel copies never race for the same name
+    let plan = plan_destinations(items, roots, target, options, true);
+    let total = items.len();
+    let done = AtomicUsize::new(0);
+
+    let results: Vec<ExportFileResult> = items
+        .par_iter()
+        .zip(plan.into_par_iter())
+        .map(|(item, planned)| {
+            let result = match planned {
+                Planned::Skip(reason) => skipped(item, reason),
+                Planned::Write(dest) => {
+                    let outcome = match transform_for(item, options) {
+                        Some(transform) => write_transformed(transform, Path::new(&item.path), &dest),
+                        None => transfer(Path::new(&item.path), &dest, options.mode),
+                    };
+                    if let Err(e) = &outcome {
+                        eprintln!("Failed to export {:?}: {}", item.path, e);
+                    }
+                    finished(item, dest.to_string_lossy().to_string(), outcome)
+                }
+            };
+
+            progress(ExportProgress {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                current: item.path.clone(),
+            });
+            result
+        })
+        .collect();
+
+    let mut report = ExportReport::from_results(results);
+
+    if let Some(format) = options.manifest {
+        let manifest_path = target.join(manifest_name(format));
+        let bytes = manifest_bytes(items, &report.files, target, format)?;
+        fs::write(&manifest_path, bytes).map_err(|e| e.to_string())?;
+        report.manifest_path = Some(manifest_path.to_string_lossy().to_string());
+    }
+
+    Ok(report)
+}
+
+/// Points `media_items.path` at the new location of every file a move export relocated.
+/// A row already stored for the destination (e.g. after overwriting a file in another library
+/// folder) is folded into the moved one. Rows moved outside every library folder are kept,
+/// stars and all, and still show up in Home and Favorites, but in no folder feed until the
+/// target is added as a folder; `left_library` counts them. A row that can't be updated turns
+/// its file's result into a failure; the others are still recorded.
+pub fn record_moves(conn: &Connection, report: &mut ExportReport) -> Result<(), String> {
+    let mut left_library = 0;
+    let mut unrecorded = 0;
+    for file in &mut report.files {
+        let (ExportStatus::Exported, Some(id), Some(dest)) = (file.status, file.id, &file.destination) else {
+            continue;
+        };
+        let recorded = health::repoint(conn, id, &normalize_path(dest)).and_then(|()| {
+            conn.query_row("SELECT folder_id IS NOT NULL FROM media_items WHERE id = ?1", params![id], |row| {
+                row.get::<_, bool>(0)
+            })
+        });
+        match recorded {
+            Ok(true) => {}
+            Ok(false) => left_library += 1,
+            Err(e) => {
+                file.status = ExportStatus::Failed;
+                file.message = Some(format!("Moved, but the library still lists the old path: {}", e));
+                unrecorded += 1;
+            }
+        }
+    }
+    report.exported -= unrecorded;
+    report.failed += unrecorded;
+    report.left_library = left_library;
+    Ok(())
+}
+
+fn transform_for<'a>(item: &MediaItem, options: &'a ExportOptions) -> Option<&'a ImageTransform> {
+    options.transform.as_ref().filter(|t| t.applies_to(item))
+}
+
+fn write_transformed(transform: &ImageTransform, src: &Path, dest: &Path) -> io::Result<()> {
+    let bytes = transform.render(src)?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, bytes)
+}
+
+fn transfer(src: &Path, dest: &Path, mode: ExportMode) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Only reached for an existing file under CollisionPolicy::Overwrite; links can't replace in place
+    if mode != ExportMode::Copy && dest.exists() {
+        fs::remove_file(dest)?;
+    }
+    match mode {
+        ExportMode::Copy | ExportMode::Zip => fs::copy(src, dest).map(|_| ()),
+        ExportMode::Move => fsops::move_file(src, dest),
+        ExportMode::Hardlink => fs::hard_link(src, dest),
+        ExportMode::Symlink => fsops::symlink_file(src, dest),
+    }
+}
+
+fn export_zip(
+    items: &[MediaItem],
+    roots: &[String],
+    archive_path: &Path,
+    options: &ExportOptions,
+    progress: &(dyn Fn(ExportProgress) + Sync),
+) -> Result<ExportReport, String> {
+    // Entry names only need to be unique inside the archive
+    let plan = plan_destinations(items, roots, Path::new(""), options, false);
+    let file = fs::File::create(archive_path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let total = items.len();
+
+    let mut results = Vec::with_capacity(total);
+    for (i, (item, planned)) in items.iter().zip(plan).enumerate() {
+        let result = match planned {
+            Planned::Skip(reason) => skipped(item, reason),
+            Planned::Write(entry) => {
+                let name = entry.to_string_lossy().replace('\\', "/");
+                let outcome = match transform_for(item, options) {
+                    Some(transform) => transform
+                        .render(Path::new(&item.path))
+                        .and_then(|bytes| add_bytes_to_zip(&mut zip, &bytes, &name)),
+                    None => add_to_zip(&mut zip, Path::new(&item.path), &name),
+                };
+                if let Err(e) = &outcome {
+                    eprintln!("Failed to archive {:?}: {}", item.path, e);
+                }
+                finished(item, name, outcome)
+            }
+        };
+        results.push(result);
+        progress(ExportProgress {
+            done: i + 1,
+            total,
+            current: item.path.clone(),
+        });
+    }
+
+    let report = ExportReport::from_results(results);
+
+    if let Some(format) = options.manifest {
+        let bytes = manifest_bytes(items, &report.files, Path::new(""), format)?;
+        add_bytes_to_zip(&mut zip, &bytes, &manifest_name(format)).map_err(|e| e.to_string())?;
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+fn zip_options(size: u64) -> zip::write::SimpleFileOptions {
+    // Media is already compressed, so storing avoids burning CPU for nothing
+    zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(size >= u32::MAX as u64)
+}
+
+fn add_to_zip(zip: &mut zip::ZipWriter<fs::File>, src: &Path, name: &str) -> io::Result<()> {
+    let mut file = fs::File::open(src)?;
+    let size = file.metadata()?.len();
+    zip.start_file(name, zip_options(size))?;
+    io::copy(&mut file, zip)?;
+    Ok(())
+}
+
+fn add_bytes_to_zip(zip: &mut zip::ZipWriter<fs::File>, bytes: &[u8], name: &str) -> io::Result<()> {
+    zip.start_file(name, zip_options(bytes.len() as u64))?;
+    zip.write_all(bytes)
+}
+
+fn skipped(item: &MediaItem, reason: String) -> ExportFileResult {
+    ExportFileResult {
+        id: item.id,
+        source: item.path.clone(),
+        destination: None,
+        status: ExportStatus::Skipped,
+        message: Some(reason),
+    }
+}
+
+fn finished(item: &MediaItem, destination: String, outcome: io::Result<()>) -> ExportFileResult {
+    let (status, message) = match outcome {
+        Ok(()) => (ExportStatus::Exported, None),
+        Err(e) => (ExportStatus::Failed, Some(e.to_string())),
+    };
+    ExportFileResult {
+        id: item.id,
+        source: item.path.clone(),
+        destination: Some(destination),
+        status,
+        message,
+    }
+}
+
+fn plan_destinations(
+    items: &[MediaItem],
+    roots: &[String],
+    target_dir: &Path,
+    options: &ExportOptions,
+    check_disk: bool,
+) -> Vec<Planned> {
+    // Compared case-insensitively, since Windows and macOS targets would clobber "a.jpg" with "A.JPG"
+    let mut claimed: HashSet<String> = HashSet::new();
+
+    items
+        .iter()
+        .map(|item| {
+            let src_path = Path::new(&item.path);
+            let Some(file_name) = src_path.file_name().map(|n| n.to_string_lossy().to_string())
+            else {
+                return Planned::Skip("source has no file name".to_string());
+            };
+
+            let dir = if options.preserve_structure {
+                relative_dir(&item.path, roots)
+                    .map(|rel| target_dir.join(rel))
+                    .unwrap_or_else(|| target_dir.to_path_buf())
+            } else {
+                target_dir.to_path_buf()
+            };
+
+            let file_name = match transform_for(item, options) {
+                Some(transform) => transform.output_name(&file_name),
+                None => file_name,
+            };
+
+            let name = match options.collision {
+                CollisionPolicy::PrefixFolder => match parent_name(src_path) {
+                    Some(parent) => format!("{}_{}", parent, file_name),
+                    None => file_name,
+                },
+                _ => file_name,
+            };
+
+            let taken_in_export = claimed.contains(&claim_key(&dir, &name));
+            let exists_on_disk = check_disk && dir.join(&name).exists();
+
+            let name = if !taken_in_export && !exists_on_disk {
+                name
+            } else {
+                match options.collision {
+                    CollisionPolicy::Skip if taken_in_export => {
+                        return Planned::Skip("name collides with another exported file".to_string())
+                    }
+                    CollisionPolicy::Skip => {
+                        return Planned::Skip("file already exists in target".to_string())
+                    }
+                    // Replacing a file written earlier in this same export would lose data silently
+                    CollisionPolicy::Overwrite if taken_in_export => {
+                        return Planned::Skip("name collides with another exported file".to_string())
+                    }
+                    CollisionPolicy::Overwrite => name,
+                    // Two folders with the same name can still collide after prefixing
+                    CollisionPolicy::Rename | CollisionPolicy::PrefixFolder => {
+                        unique_name(&name, &dir, &claimed, check_disk)
+                    }
+                }
+            };
+
+            claimed.insert(claim_key(&dir, &name));
+            Planned::Write(dir.join(name))
+        })
+        .collect()
+}
+
+fn claim_key(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().to_lowercase()
+}
+
+/// Directory of `path` relative to the longest matching root, prefixed with the root's own name.
+fn relative_dir(path: &str, roots: &[String]) -> Option<PathBuf> {
+    let path = path.replace('\\', "/");
+    let root = roots
+        .iter()
+        .map(|r| r.replace('\\', "/").trim_end_matches('/').to_string())
+        .filter(|r| path.starts_with(&format!("{}/", r)))
+        .max_by_key(|r| r.len())?;
+
+    let root_name = Path::new(&root)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+
+    let rel = &path[root.len() + 1..];
+    let rel_dir = Path::new(rel).parent().unwrap_or(Path::new(""));
+    Some(Path::new(&root_name).join(rel_dir))
+}
+
+fn parent_name(path: &Path) -> Option<String> {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+}
+
+fn unique_name(name: &str, dir: &Path, claimed: &HashSet<String>, check_disk: bool) -> String {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+
+    let mut n = 1;
+    loop {
+        let candidate = match &ext {
+            Some(ext) => format!("{} ({}).{}", stem, n, ext),
+            None => format!("{} ({})", stem, n),
+        };
+        let taken = claimed.contains(&claim_key(dir, &candidate))
+            || (check_disk && dir.join(&candidate).exists());
+        if !taken {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[derive(Serialize)]
+struct ManifestEntry<'a> {
+    source: &'a str,
+    destination: Option<String>,
+    status: ExportStatus,
+    file_type: &'a str,
+    size_bytes: i64,
+    created_at: i64,
+    width: Option<i32>,
+    height: Option<i32>,
+    duration_sec: Option<f64>,
+    starred: bool,
+}
+
+fn manifest_name(format: ManifestFormat) -> String {
+    match format {
+        ManifestFormat::Json => format!("{}.json", MANIFEST_NAME),
+        ManifestFormat::Csv => format!("{}.csv", MANIFEST_NAME),
+    }
+}
+
+fn manifest_bytes(
+    items: &[MediaItem],
+    results: &[ExportFileResult],
+    base: &Path,
+    format: ManifestFormat,
+) -> Result<Vec<u8>, String> {
+    let entries: Vec<ManifestEntry> = items
+        .iter()
+        .zip(results)
+        .map(|(item, result)| ManifestEntry {
+            source: &item.path,
+            // Relative to the manifest, so the export folder can be moved as a whole
+            destination: result.destination.as_ref().map(|d| {
+                Path::new(d)
+                    .strip_prefix(base)
+                    .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_else(|_| d.clone())
+            }),
+            status: result.status,
+            file_type: &item.file_type,
+            size_bytes: item.size_bytes,
+            created_at: item.created_at,
+            width: item.width,
+            height: item.height,
+            duration_sec: item.duration_sec,
+            starred: item.starred,
+        })
+        .collect();
+
+    match format {
+        ManifestFormat::Json => serde_json::to_vec_pretty(&entries).map_err(|e| e.to_string()),
+        ManifestFormat::Csv => {
+            let mut out = Vec::new();
+            writeln!(
+                out,
+                "source,destination,status,file_type,size_bytes,created_at,width,height,duration_sec,starred"
+            )
+            .map_err(|e| e.to_string())?;
+            for e in &entries {
+                let status = match e.status {
+                    ExportStatus::Exported => "exported",
+                    ExportStatus::Skipped => "skipped",
+                    ExportStatus::Failed => "failed",
+                };
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    csv_field(e.source),
+                    csv_field(e.destination.as_deref().unwrap_or("")),
+                    status,
+                    e.file_type,
+                    e.size_bytes,
+                    e.created_at,
+                    opt(e.width),
+                    opt(e.height),
+                    opt(e.duration_sec),
+                    e.starred
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            Ok(out)
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,220 @@
+use crate::db::{changes, Feed, FilterOptions};
+use crate::normalize_path;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const FORMAT: &str = "xcroller-feeds";
+const VERSION: u32 = 1;
+
+/// On-disk layout of a shared feeds file. Bump `VERSION` when fields change meaning.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeedDocument {
+    pub format: String,
+    pub version: u32,
+    pub feeds: Vec<PortableFeed>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PortableFeed {
+    pub name: String,
+    pub filter_config: serde_json::Value,
+    /// Folders as they were on the exporting machine; remapped on import
+    #[serde(default)]
+    pub folder_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FolderMapping {
+    pub source: String,
+    /// Whether `source` is (inside) a library folder here and can be kept as-is
+    pub found: bool,
+    /// A library folder with the same name, offered when `source` isn't found
+    pub suggestion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeedPreview {
+    pub name: String,
+    pub folder_paths: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeedImportPreview {
+    pub feeds: Vec<FeedPreview>,
+    pub folders: Vec<FolderMapping>,
+}
+
+/// Writes the feeds in `ids` (all feeds when `None`) to `target`.
+pub fn export_feeds(conn: &Connection, ids: Option<&[i64]>, target: &Path) -> Result<usize, String> {
+    let feeds: Vec<Feed> = changes::get_feeds(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|f| ids.is_none_or(|ids| f.id.is_some_and(|id| ids.contains(&id))))
+        .collect();
+
+    let mut portable = Vec::with_capacity(feeds.len());
+    for feed in &feeds {
+        let mut filter_config: serde_json::Value =
+            serde_json::from_str(&feed.filter_config).map_err(|e| format!("Feed \"{}\": {}", feed.name, e))?;
+        // Folders travel separately so they can be remapped
+        if let Some(obj) = filter_config.as_object_mut() {
+            obj.remove("folder_paths");
+        }
+        portable.push(PortableFeed {
+            name: feed.name.clone(),
+            filter_config,
+            folder_paths: serde_json::from_str(&feed.folder_paths)
+                .map_err(|e| format!("Feed \"{}\": {}", feed.name, e))?,
+        });
+    }
+
+    let document = FeedDocument {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        feeds: portable,
+    };
+    let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    fs::write(target, json).map_err(|e| e.to_string())?;
+    Ok(feeds.len())
+}
+
+fn read_document(path: &Path) -> Result<FeedDocument, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let document: FeedDocument =
+        serde_json::from_str(&text).map_err(|e| format!("Not an Xcroller feeds file: {}", e))?;
+    if document.format != FORMAT {
+        return Err("Not an Xcroller feeds file".to_string());
+    }
+    if document.version > VERSION {
+        return Err(format!(
+            "This file was written by a newer Xcroller (format version {}); please update",
+            document.version
+        ));
+    }
+    Ok(document)
+}
+
+fn parse_filters(feed: &PortableFeed) -> Result<FilterOptions, String> {
+    let mut filters: FilterOptions = serde_json::from_value(feed.filter_config.clone())
+        .map_err(|e| format!("Invalid filters: {}", e))?;
+    filters.validate()?;
+    filters.folder_paths = None;
+    Ok(filters)
+}
+
+/// Stored like the frontend stores it: unset filters are left out rather than `null`.
+fn filter_config_json(filters: &FilterOptions) -> Result<String, String> {
+    let mut value = serde_json::to_value(filters).map_err(|e| e.to_string())?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.retain(|_, v| !v.is_null());
+    }
+    Ok(value.to_string())
+}
+
+fn in_library(path: &str, roots: &[String]) -> bool {
+    roots.iter().any(|r| {
+        let r = r.trim_end_matches('/');
+        path == r || path.starts_with(&format!("{}/", r))
+    })
+}
+
+/// Reads `path` and reports which feeds are valid and which folders need a mapping.
+pub fn inspect_import(conn: &Connection, path: &Path) -> Result<FeedImportPreview, String> {
+    let document = read_document(path)?;
+    let roots: Vec<String> = changes::get_folders(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+
+    let feeds = document
+        .feeds
+        .iter()
+        .map(|feed| FeedPreview {
+            name: feed.name.clone(),
+            folder_paths: feed.folder_paths.clone(),
+            error: parse_filters(feed).err(),
+        })
+        .collect();
+
+    let mut folders: Vec<FolderMapping> = Vec::new();
+    for source in document.feeds.iter().flat_map(|f| &f.folder_paths) {
+        if folders.iter().any(|m| &m.source == source) {
+            continue;
+        }
+        let found = in_library(&normalize_path(source), &roots);
+        let name = source.replace('\\', "/").trim_end_matches('/').rsplit('/').next().map(str::to_lowercase);
+        let suggestion = if found {
+            None
+        } else {
+            roots
+                .iter()
+                .find(|r| r.trim_end_matches('/').rsplit('/').next().map(str::to_lowercase) == name)
+                .cloned()
+        };
+        folders.push(FolderMapping {
+            source: source.clone(),
+            found,
+            suggestion,
+        });
+    }
+
+    Ok(FeedImportPreview { feeds, folders })
+}
+
+/// Imports every feed in `path`, all or nothing. `folder_map` replaces source folders;
+/// an empty target drops that folder. Folders that aren't in this library must be mapped.
+pub fn import_feeds(conn: &Connection, path: &Path, folder_map: &HashMap<String, String>) -> Result<usize, String> {
+    let document = read_document(path)?;
+    let roots: Vec<String> = changes::get_folders(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+
+    let mut feeds = Vec::with_capacity(document.feeds.len());
+    for feed in &document.feeds {
+        let filters = parse_filters(feed).map_err(|e| format!("Feed \"{}\": {}", feed.name, e))?;
+
+        let mut folder_paths: Vec<String> = Vec::new();
+        for source in &feed.folder_paths {
+            let target = match folder_map.get(source) {
+                Some(target) if target.trim().is_empty() => continue,
+                Some(target) => normalize_path(target),
+                None => normalize_path(source),
+            };
+            if !in_library(&target, &roots) {
+                return Err(format!(
+                    "Feed \"{}\": {} is not a library folder on this machine; choose where it maps to",
+                    feed.name, target
+                ));
+            }
+            if !folder_paths.contains(&target) {
+                folder_paths.push(target);
+            }
+        }
+        // No folders would silently widen the feed to the whole library
+        if folder_paths.is_empty() && !feed.folder_paths.is_empty() {
+            return Err(format!("Feed \"{}\" has no folders left after remapping", feed.name));
+        }
+
+        feeds.push(Feed {
+            id: None,
+            name: feed.name.clone(),
+            folder_paths: serde_json::to_string(&folder_paths).map_err(|e| e.to_string())?,
+            filter_config: filter_config_json(&filters)?,
+        });
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let count = feeds.len();
+    for feed in feeds {
+        changes::save_feed(&tx, feed).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(count)
+}
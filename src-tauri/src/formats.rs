@@ -0,0 +1,532 @@
+use crate::animation;
+use crate::db::changes;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const ENABLED_SETTING: &str = "enabled_formats";
+
+// Enough for every magic check below, including two MPEG-TS packets
+const HEADER_LEN: usize = 512;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+impl MediaKind {
+    /// The value stored in `media_items.file_type`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prober {
+    ImageHeader,
+    /// Header dimensions plus frame timing for formats that can animate
+    AnimatedImage,
+    Mp4,
+    Matroska,
+    Avi,
+    MpegTs,
+}
+
+/// What a prober could read from the file; any field may be missing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Probe {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_sec: Option<f64>,
+    /// Images only: 1 for stills
+    pub frame_count: Option<i64>,
+    pub loop_count: Option<i64>,
+}
+
+pub struct Format {
+    pub id: &'static str,
+    pub extensions: &'static [&'static str],
+    pub mime: &'static str,
+    pub kind: MediaKind,
+    pub prober: Prober,
+    /// Whether the `image` crate can decode it (thumbnails, export transforms)
+    pub decodable: bool,
+    magic: fn(&[u8]) -> bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FormatInfo {
+    pub id: String,
+    pub extensions: Vec<String>,
+    pub mime: String,
+    pub kind: MediaKind,
+    pub enabled: bool,
+}
+
+/// Every format Xcroller can index. Order matters for sniffing: the ISO BMFF
+/// family (HEIF, AVIF, 3GP, MOV) must come before the MP4 catch-all, WebM before Matroska.
+#[rustfmt::skip]
+pub const FORMATS: &[Format] = &[
+    Format { id: "jpeg", extensions: &["jpg", "jpeg", "jpe", "jfif"], mime: "image/jpeg", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: true, magic: is_jpeg },
+    Format { id: "png", extensions: &["png", "apng"], mime: "image/png", kind: MediaKind::Image, prober: Prober::AnimatedImage, decodable: true, magic: is_png },
+    Format { id: "gif", extensions: &["gif"], mime: "image/gif", kind: MediaKind::Image, prober: Prober::AnimatedImage, decodable: true, magic: is_gif },
+    Format { id: "webp", extensions: &["webp"], mime: "image/webp", kind: MediaKind::Image, prober: Prober::AnimatedImage, decodable: true, magic: is_webp },
+    Format { id: "bmp", extensions: &["bmp", "dib"], mime: "image/bmp", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: true, magic: is_bmp },
+    Format { id: "tiff", extensions: &["tif", "tiff"], mime: "image/tiff", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: true, magic: is_tiff },
+    Format { id: "avif", extensions: &["avif"], mime: "image/avif", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: false, magic: is_avif },
+    Format { id: "heic", extensions: &["heic", "heif"], mime: "image/heic", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: false, magic: is_heic },
+    Format { id: "jxl", extensions: &["jxl"], mime: "image/jxl", kind: MediaKind::Image, prober: Prober::ImageHeader, decodable: false, magic: is_jxl },
+    Format { id: "3gp", extensions: &["3gp", "3g2"], mime: "video/3gpp", kind: MediaKind::Video, prober: Prober::Mp4, decodable: false, magic: is_3gp },
+    Format { id: "mov", extensions: &["mov", "qt"], mime: "video/quicktime", kind: MediaKind::Video, prober: Prober::Mp4, decodable: false, magic: is_mov },
+    Format { id: "mp4", extensions: &["mp4", "m4v"], mime: "video/mp4", kind: MediaKind::Video, prober: Prober::Mp4, decodable: false, magic: is_mp4 },
+    Format { id: "webm", extensions: &["webm"], mime: "video/webm", kind: MediaKind::Video, prober: Prober::Matroska, decodable: false, magic: is_webm },
+    Format { id: "mkv", extensions: &["mkv"], mime: "video/x-matroska", kind: MediaKind::Video, prober: Prober::Matroska, decodable: false, magic: is_ebml },
+    Format { id: "avi", extensions: &["avi"], mime: "video/x-msvideo", kind: MediaKind::Video, prober: Prober::Avi, decodable: false, magic: is_avi },
+    Format { id: "mpeg_ts", extensions: &["ts", "mts", "m2ts"], mime: "video/mp2t", kind: MediaKind::Video, prober: Prober::MpegTs, decodable: false, magic: is_mpeg_ts },
+];
+
+pub fn by_id(id: &str) -> Option<&'static Format> {
+    FORMATS.iter().find(|f| f.id == id)
+}
+
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
+
+/// Format implied by the file name alone; prefer `detect` when the file can be read.
+pub fn by_extension(path: &Path) -> Option<&'static Format> {
+    let ext = extension_of(path)?;
+    FORMATS.iter().find(|f| f.extensions.contains(&ext.as_str()))
+}
+
+/// Identifies a format from the first bytes of a file.
+pub fn sniff(header: &[u8]) -> Option<&'static Format> {
+    FORMATS.iter().find(|f| (f.magic)(header))
+}
+
+/// Format of the file at `path`, judged by its content. Only files with a known media
+/// extension are opened, so a scan doesn't read every document in the folder; a `.jpg`
+/// that is really a PNG still counts, while a TypeScript `.ts` file doesn't.
+pub fn detect(path: &Path) -> Option<&'static Format> {
+    by_extension(path)?;
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path).ok()?.take(HEADER_LEN as u64).read_to_end(&mut header).ok()?;
+    sniff(&header)
+}
+
+/// Ids of the formats a scan picks up. Everything is enabled until the user says otherwise.
+pub fn enabled_ids(conn: &Connection) -> Vec<&'static str> {
+    let stored: Option<Vec<String>> = changes::get_setting(conn, ENABLED_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok());
+    match stored {
+        Some(ids) => FORMATS.iter().map(|f| f.id).filter(|id| ids.iter().any(|s| s == id)).collect(),
+        None => FORMATS.iter().map(|f| f.id).collect(),
+    }
+}
+
+pub fn set_enabled(conn: &Connection, ids: &[String]) -> Result<(), String> {
+    if let Some(unknown) = ids.iter().find(|id| by_id(id).is_none()) {
+        return Err(format!("Unknown format: {}", unknown));
+    }
+    if ids.is_empty() {
+        return Err("Enable at least one format".to_string());
+    }
+    let value = serde_json::to_string(ids).map_err(|e| e.to_string())?;
+    changes::set_setting(conn, ENABLED_SETTING, &value).map_err(|e| e.to_string())
+}
+
+pub fn list(conn: &Connection) -> Vec<FormatInfo> {
+    let enabled = enabled_ids(conn);
+    FORMATS
+        .iter()
+        .map(|f| FormatInfo {
+            id: f.id.to_string(),
+            extensions: f.extensions.iter().map(|e| e.to_string()).collect(),
+            mime: f.mime.to_string(),
+            kind: f.kind,
+            enabled: enabled.contains(&f.id),
+        })
+        .collect()
+}
+
+impl Format {
+    /// Reads dimensions and/or duration without decoding the media.
+    pub fn probe(&self, path: &Path) -> io::Result<Probe> {
+        match self.prober {
+            Prober::ImageHeader => probe_image(path),
+            Prober::AnimatedImage => {
+                let mut probe = probe_image(path)?;
+                if let Some(animation) = animation::inspect(path, self.id)? {
+                    probe.frame_count = Some(animation.frame_count);
+                    probe.loop_count = Some(animation.loop_count);
+                    probe.duration_sec = Some(animation.duration_sec);
+                }
+                Ok(probe)
+            }
+            Prober::Mp4 => probe_mp4(path),
+            Prober::Matroska => probe_matroska(path),
+            Prober::Avi => probe_avi(path),
+            Prober::MpegTs => probe_mpeg_ts(path),
+        }
+    }
+}
+
+// --- Magic bytes ---
+
+fn is_jpeg(h: &[u8]) -> bool {
+    h.starts_with(&[0xFF, 0xD8, 0xFF])
+}
+
+fn is_png(h: &[u8]) -> bool {
+    h.starts_with(b"\x89PNG\r\n\x1a\n")
+}
+
+fn is_gif(h: &[u8]) -> bool {
+    h.starts_with(b"GIF87a") || h.starts_with(b"GIF89a")
+}
+
+fn is_riff(h: &[u8], form: &[u8; 4]) -> bool {
+    h.len() >= 12 && h.starts_with(b"RIFF") && &h[8..12] == form
+}
+
+fn is_webp(h: &[u8]) -> bool {
+    is_riff(h, b"WEBP")
+}
+
+fn is_avi(h: &[u8]) -> bool {
+    is_riff(h, b"AVI ")
+}
+
+fn is_bmp(h: &[u8]) -> bool {
+    h.starts_with(b"BM") && h.len() >= 14
+}
+
+fn is_tiff(h: &[u8]) -> bool {
+    h.starts_with(b"II*\0") || h.starts_with(b"MM\0*")
+}
+
+fn is_jxl(h: &[u8]) -> bool {
+    h.starts_with(&[0xFF, 0x0A]) || h.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n")
+}
+
+/// Major brand followed by the compatible brands of a leading `ftyp` box.
+fn ftyp_brands(h: &[u8]) -> Option<Vec<&[u8]>> {
+    if h.len() < 12 || &h[4..8] != b"ftyp" {
+        return None;
+    }
+    let size = (u32::from_be_bytes([h[0], h[1], h[2], h[3]]) as usize).clamp(12, h.len());
+    let compatible = h.get(16..size).unwrap_or(&[]).chunks_exact(4);
+    Some(std::iter::once(&h[8..12]).chain(compatible).collect())
+}
+
+fn major_brand(h: &[u8]) -> Option<&[u8]> {
+    ftyp_brands(h).map(|brands| brands[0])
+}
+
+fn is_avif(h: &[u8]) -> bool {
+    ftyp_brands(h).is_some_and(|brands| {
+        matches!(brands[0], b"avif" | b"avis") || (brands[0] == b"mif1" && brands.contains(&&b"avif"[..]))
+    })
+}
+
+fn is_heic(h: &[u8]) -> bool {
+    major_brand(h).is_some_and(|b| {
+        matches!(b, b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1")
+    })
+}
+
+fn is_3gp(h: &[u8]) -> bool {
+    major_brand(h).is_some_and(|b| b.starts_with(b"3g"))
+}
+
+fn is_mov(h: &[u8]) -> bool {
+    match major_brand(h) {
+        Some(brand) => brand == b"qt  ",
+        // Old QuickTime files have no ftyp and start straight with an atom
+        None => h.len() >= 8 && matches!(&h[4..8], b"moov" | b"mdat" | b"wide" | b"free" | b"skip" | b"pnot"),
+    }
+}
+
+fn is_mp4(h: &[u8]) -> bool {
+    ftyp_brands(h).is_some()
+}
+
+fn is_ebml(h: &[u8]) -> bool {
+    h.starts_with(&[0x1A, 0x45, 0xDF, 0xA3])
+}
+
+fn is_webm(h: &[u8]) -> bool {
+    // The DocType string sits in the EBML header, well within the first 64 bytes
+    is_ebml(h) && h[..h.len().min(64)].windows(4).any(|w| w == b"webm")
+}
+
+/// Sync bytes at the packet stride: 188-byte packets, or 192 with a timestamp prefix (M2TS).
+fn ts_layout(h: &[u8]) -> Option<(usize, usize)> {
+    [(188, 0), (192, 4)]
+        .into_iter()
+        .find(|&(stride, offset)| (0..2).all(|i| h.get(offset + i * stride) == Some(&0x47)))
+}
+
+fn is_mpeg_ts(h: &[u8]) -> bool {
+    ts_layout(h).is_some()
+}
+
+// --- Probers ---
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn probe_image(path: &Path) -> io::Result<Probe> {
+    let size = imagesize::size(path).map_err(|e| invalid(&e.to_string()))?;
+    Ok(Probe {
+        width: Some(size.width as i32),
+        height: Some(size.height as i32),
+        frame_count: Some(1),
+        ..Probe::default()
+    })
+}
+
+fn probe_mp4(path: &Path) -> io::Result<Probe> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size).map_err(|e| invalid(&e.to_string()))?;
+    let video = reader
+        .tracks()
+        .values()
+        .find(|t| matches!(t.track_type(), Ok(mp4::TrackType::Video)));
+    Ok(Probe {
+        width: video.map(|t| t.width() as i32).filter(|w| *w > 0),
+        height: video.map(|t| t.height() as i32).filter(|h| *h > 0),
+        duration_sec: Some(reader.duration().as_secs_f64()),
+        ..Probe::default()
+    })
+}
+
+const EBML_SEGMENT: u64 = 0x18538067;
+const EBML_INFO: u64 = 0x1549A966;
+const EBML_TIMECODE_SCALE: u64 = 0x2AD7B1;
+const EBML_DURATION: u64 = 0x4489;
+const EBML_TRACKS: u64 = 0x1654AE6B;
+const EBML_TRACK_ENTRY: u64 = 0xAE;
+const EBML_VIDEO: u64 = 0xE0;
+const EBML_PIXEL_WIDTH: u64 = 0xB0;
+const EBML_PIXEL_HEIGHT: u64 = 0xBA;
+const EBML_CLUSTER: u64 = 0x1F43B675;
+
+/// EBML variable-length integer. IDs keep their length marker, sizes don't.
+/// The flag reports the reserved all-ones value ("unknown size").
+fn read_vint<R: Read>(r: &mut R, keep_marker: bool) -> io::Result<(u64, bool)> {
+    let mut byte = [0u8; 1];
+    r.read_exact(&mut byte)?;
+    let len = byte[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(invalid("Bad EBML length"));
+    }
+    let mask = (0xFFu16 >> len) as u8;
+    let mut value = if keep_marker { byte[0] } else { byte[0] & mask } as u64;
+    let mut all_ones = byte[0] & mask == mask;
+    for _ in 1..len {
+        r.read_exact(&mut byte)?;
+        value = (value << 8) | byte[0] as u64;
+        all_ones &= byte[0] == 0xFF;
+    }
+    Ok((value, all_ones))
+}
+
+/// Next element before `end`: its ID and where it ends. The reader is left at its data.
+fn ebml_child<R: Read + Seek>(r: &mut R, end: u64) -> io::Result<Option<(u64, u64)>> {
+    if r.stream_position()? >= end {
+        return Ok(None);
+    }
+    let (id, _) = read_vint(r, true)?;
+    let (size, unknown) = read_vint(r, false)?;
+    let start = r.stream_position()?;
+    let child_end = if unknown { end } else { start.saturating_add(size).min(end) };
+    Ok(Some((id, child_end)))
+}
+
+fn ebml_data<R: Read + Seek>(r: &mut R, end: u64) -> io::Result<Vec<u8>> {
+    let len = end.saturating_sub(r.stream_position()?);
+    if len > 8 {
+        return Err(invalid("EBML number too long"));
+    }
+    let mut data = vec![0u8; len as usize];
+    r.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn ebml_uint<R: Read + Seek>(r: &mut R, end: u64) -> io::Result<u64> {
+    Ok(ebml_data(r, end)?.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+fn ebml_float<R: Read + Seek>(r: &mut R, end: u64) -> io::Result<f64> {
+    let data = ebml_data(r, end)?;
+    match data.len() {
+        4 => Ok(f32::from_be_bytes([data[0], data[1], data[2], data[3]]) as f64),
+        8 => Ok(f64::from_be_bytes(data.try_into().expect("8 bytes"))),
+        _ => Err(invalid("Bad EBML float")),
+    }
+}
+
+/// Duration from the segment info and size from the first video track. Both precede
+/// the first cluster in every muxer we've seen, so reading stops there.
+fn probe_matroska(path: &Path) -> io::Result<Probe> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut r = BufReader::new(file);
+    let mut probe = Probe::default();
+
+    while let Some((id, end)) = ebml_child(&mut r, len)? {
+        if id == EBML_SEGMENT {
+            read_segment(&mut r, end, &mut probe)?;
+            break;
+        }
+        r.seek(SeekFrom::Start(end))?;
+    }
+    Ok(probe)
+}
+
+fn read_segment<R: Read + Seek>(r: &mut R, end: u64, probe: &mut Probe) -> io::Result<()> {
+    let mut timecode_scale = 1_000_000u64; // Nanoseconds per tick, the spec's default
+    let mut duration = None;
+
+    while let Some((id, child_end)) = ebml_child(r, end)? {
+        match id {
+            EBML_INFO => {
+                while let Some((id, field_end)) = ebml_child(r, child_end)? {
+                    match id {
+                        EBML_TIMECODE_SCALE => timecode_scale = ebml_uint(r, field_end)?,
+                        EBML_DURATION => duration = Some(ebml_float(r, field_end)?),
+                        _ => {}
+                    }
+                    r.seek(SeekFrom::Start(field_end))?;
+                }
+            }
+            EBML_TRACKS if probe.width.is_none() => {
+                while let Some((id, entry_end)) = ebml_child(r, child_end)? {
+                    if id == EBML_TRACK_ENTRY {
+                        read_track_entry(r, entry_end, probe)?;
+                    }
+                    r.seek(SeekFrom::Start(entry_end))?;
+                }
+            }
+            EBML_CLUSTER => break,
+            _ => {}
+        }
+        r.seek(SeekFrom::Start(child_end))?;
+    }
+
+    probe.duration_sec = duration
+        .map(|ticks| ticks * timecode_scale as f64 / 1e9)
+        .filter(|secs| secs.is_finite() && *secs > 0.0);
+    Ok(())
+}
+
+fn read_track_entry<R: Read + Seek>(r: &mut R, end: u64, probe: &mut Probe) -> io::Result<()> {
+    while let Some((id, child_end)) = ebml_child(r, end)? {
+        if id == EBML_VIDEO && probe.width.is_none() {
+            while let Some((id, field_end)) = ebml_child(r, child_end)? {
+                match id {
+                    EBML_PIXEL_WIDTH => probe.width = Some(ebml_uint(r, field_end)? as i32),
+                    EBML_PIXEL_HEIGHT => probe.height = Some(ebml_uint(r, field_end)? as i32),
+                    _ => {}
+                }
+                r.seek(SeekFrom::Start(field_end))?;
+            }
+        }
+        r.seek(SeekFrom::Start(child_end))?;
+    }
+    Ok(())
+}
+
+/// Reads the main AVI header (`avih`). For OpenDML files over 1 GB the frame count only
+/// covers the first RIFF chunk, so the duration comes out short.
+fn probe_avi(path: &Path) -> io::Result<Probe> {
+    let mut head = Vec::new();
+    File::open(path)?.take(4096).read_to_end(&mut head)?;
+    let at = head
+        .windows(4)
+        .position(|w| w == b"avih")
+        .ok_or_else(|| invalid("No AVI header"))?;
+    let avih = head.get(at + 8..at + 8 + 40).ok_or_else(|| invalid("Truncated AVI header"))?;
+    let u32_at = |i: usize| u32::from_le_bytes([avih[i], avih[i + 1], avih[i + 2], avih[i + 3]]);
+
+    let (micros_per_frame, total_frames) = (u32_at(0), u32_at(16));
+    let (width, height) = (u32_at(32), u32_at(36));
+    Ok(Probe {
+        width: (width > 0).then_some(width as i32),
+        height: (height > 0).then_some(height as i32),
+        duration_sec: (micros_per_frame > 0 && total_frames > 0)
+            .then(|| micros_per_frame as f64 * total_frames as f64 / 1e6),
+        ..Probe::default()
+    })
+}
+
+// PCRs are in 90 kHz units and wrap at 2^33
+const PCR_HZ: f64 = 90_000.0;
+const PCR_WRAP: u64 = 1 << 33;
+const TS_SCAN_BYTES: u64 = 1024 * 1024;
+
+/// (PID, PCR base) for every packet in `buf` that carries a program clock reference.
+fn pcrs(buf: &[u8], stride: usize) -> Vec<(u16, u64)> {
+    let Some(first) = (0..stride.min(buf.len())).find(|&i| {
+        (0..3).all(|k| buf.get(i + k * stride) == Some(&0x47))
+    }) else {
+        return Vec::new();
+    };
+    buf[first..]
+        .chunks(stride)
+        .filter(|p| p.len() >= 11 && p[0] == 0x47)
+        .filter(|p| p[3] & 0x20 != 0 && p[4] >= 7 && p[5] & 0x10 != 0)
+        .map(|p| {
+            let pid = (((p[1] & 0x1F) as u16) << 8) | p[2] as u16;
+            let base = ((p[6] as u64) << 25)
+                | ((p[7] as u64) << 17)
+                | ((p[8] as u64) << 9)
+                | ((p[9] as u64) << 1)
+                | ((p[10] as u64) >> 7);
+            (pid, base)
+        })
+        .collect()
+}
+
+/// Duration from the first and last PCR of the first program; no dimensions, which
+/// would take parsing the video bitstream.
+fn probe_mpeg_ts(path: &Path) -> io::Result<Probe> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut head = Vec::new();
+    (&mut file).take(TS_SCAN_BYTES).read_to_end(&mut head)?;
+    let (stride, offset) = ts_layout(&head).ok_or_else(|| invalid("Not an MPEG transport stream"))?;
+    let head_pcrs = pcrs(&head[offset..], stride);
+    let Some(&(pid, first)) = head_pcrs.first() else {
+        return Ok(Probe::default());
+    };
+
+    file.seek(SeekFrom::Start(len.saturating_sub(TS_SCAN_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let last = pcrs(&tail, stride)
+        .into_iter()
+        .rev()
+        .find(|(p, _)| *p == pid)
+        .map(|(_, base)| base)
+        .unwrap_or(first);
+
+    let ticks = (last + PCR_WRAP - first) % PCR_WRAP;
+    Ok(Probe {
+        duration_sec: (ticks > 0).then(|| ticks as f64 / PCR_HZ),
+        ..Probe::default()
+    })
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const HASH_SAMPLE: u64 = 64 * 1024;
+
+/// Moves a file, falling back to copy + delete when `rename` can't cross filesystems.
+pub fn move_file(src: &Path, dest: &Path) -> io::Result<()> {
+    match fs::rename(src, dest) {
+        Ok(()) => Ok(()),
+        Err(_) if src.is_file() => {
+            // Across volumes; a copy cut short is removed rather than left behind
+            if let Err(e) = fs::copy(src, dest) {
+                let _ = fs::remove_file(dest);
+                return Err(e);
+            }
+            if let Err(e) = fs::remove_file(src) {
+                // Leave the original in place rather than ending up with two copies
+                let _ = fs::remove_file(dest);
+                return Err(e);
+            }
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub fn symlink_file(src: &Path, dest: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(src, dest)
+    }
+    #[cfg(windows)]
+    {
+        std::os::windows::fs::symlink_file(src, dest)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (src, dest);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "symlinks are not supported on this platform",
+        ))
+    }
+}
+
+/// Whether both paths name the same file on disk, e.g. "a.jpg" and "A.jpg" on a
+/// case-insensitive filesystem. False if either doesn't exist.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        // Resolves to the name as stored, so different spellings of one file compare equal
+        match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Cheap content fingerprint: the size plus the first and last 64 KiB.
+/// Good enough to recognise a moved file without reading whole videos.
+pub fn quick_hash(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut data = Vec::with_capacity(2 * HASH_SAMPLE as usize + 8);
+    data.extend_from_slice(&len.to_le_bytes());
+    file.by_ref().take(HASH_SAMPLE).read_to_end(&mut data)?;
+    if len > 2 * HASH_SAMPLE {
+        file.seek(SeekFrom::Start(len - HASH_SAMPLE))?;
+        file.take(HASH_SAMPLE).read_to_end(&mut data)?;
+    } else if len > HASH_SAMPLE {
+        file.read_to_end(&mut data)?;
+    }
+    Ok(format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&data)))
+}
@@ -0,0 +1,277 @@
+use crate::db::changes;
+use crate::{formats, fsops, trash};
+use rayon::prelude::*;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use walkdir::WalkDir;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Offline,    // The library folder itself is unreachable (e.g. unplugged drive)
+    Missing,    // Folder is there, file isn't
+    Unreadable, // Permission denied or other I/O error
+    Corrupt,    // Opens, but doesn't decode
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthIssue {
+    pub id: i64,
+    pub path: String,
+    pub file_type: String,
+    pub status: HealthStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Relink {
+    pub id: i64,
+    pub from: String,
+    pub to: String,
+    pub matched_by: String, // "hash" or "name_size"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RelinkReport {
+    pub relinked: Vec<Relink>,
+    /// Several files matched equally well; left for the user to sort out
+    pub ambiguous: Vec<i64>,
+    pub not_found: Vec<i64>,
+}
+
+struct Row {
+    id: i64,
+    path: String,
+    file_type: String,
+    size_bytes: i64,
+    quick_hash: Option<String>,
+}
+
+fn load_rows(conn: &Connection, ids: Option<&[i64]>) -> Result<Vec<Row>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, path, file_type, size_bytes, quick_hash FROM media_items WHERE trashed_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Row {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                file_type: row.get(2)?,
+                size_bytes: row.get(3)?,
+                quick_hash: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .filter(|row| ids.is_none_or(|ids| ids.contains(&row.id)))
+        .collect();
+    Ok(rows)
+}
+
+/// Checks every row's file. `deep` fully decodes images instead of reading just the header.
+pub fn check_library(
+    conn: &Connection,
+    deep: bool,
+    progress: &(dyn Fn(HealthProgress) + Sync),
+) -> Result<Vec<HealthIssue>, String> {
+    let roots: Vec<String> = changes::get_folders(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|f| f.path.trim_end_matches('/').to_string())
+        .collect();
+    let rows = load_rows(conn, None)?;
+    let total = rows.len();
+    let done = AtomicUsize::new(0);
+
+    let mut issues: Vec<HealthIssue> = rows
+        .par_iter()
+        .filter_map(|row| {
+            let result = check_file(row, &roots, deep).map(|(status, message)| HealthIssue {
+                id: row.id,
+                path: row.path.clone(),
+                file_type: row.file_type.clone(),
+                status,
+                message,
+            });
+            progress(HealthProgress {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+            });
+            result
+        })
+        .collect();
+    issues.sort_by_key(|issue| issue.id);
+    Ok(issues)
+}
+
+fn check_file(row: &Row, roots: &[String], deep: bool) -> Option<(HealthStatus, Option<String>)> {
+    let path = Path::new(&row.path);
+    match fs::File::open(path) {
+        Ok(mut file) => {
+            let mut first = [0u8; 1];
+            if let Err(e) = file.read(&mut first) {
+                return Some((HealthStatus::Unreadable, Some(e.to_string())));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let root_reachable = roots
+                .iter()
+                .find(|r| row.path.starts_with(&format!("{}/", r)))
+                .is_some_and(|r| Path::new(r).is_dir());
+            let status = if root_reachable {
+                HealthStatus::Missing
+            } else {
+                HealthStatus::Offline
+            };
+            return Some((status, None));
+        }
+        Err(e) => return Some((HealthStatus::Unreadable, Some(e.to_string()))),
+    }
+
+    decode_error(path, deep).map(|e| (HealthStatus::Corrupt, Some(e)))
+}
+
+fn decode_error(path: &Path, deep: bool) -> Option<String> {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size == 0 {
+        return Some("File is empty".to_string());
+    }
+
+    let Some(format) = formats::detect(path) else {
+        return Some("Not a recognised image or video file".to_string());
+    };
+    if let Err(e) = format.probe(path) {
+        return Some(e.to_string());
+    }
+    if deep && format.decodable {
+        return image::open(path).err().map(|e| e.to_string());
+    }
+    None
+}
+
+/// Looks for the files behind `ids` under `search_root` and repoints their rows.
+/// A stored fingerprint finds renamed files too; without one, name and size must match exactly once.
+pub fn relink(conn: &Connection, ids: &[i64], search_root: &Path) -> Result<RelinkReport, String> {
+    let rows: Vec<Row> = load_rows(conn, Some(ids))?
+        .into_iter()
+        .filter(|row| !Path::new(&row.path).exists())
+        .collect();
+    let mut report = RelinkReport::default();
+    if rows.is_empty() {
+        return Ok(report);
+    }
+
+    let wanted_sizes: Vec<u64> = rows.iter().map(|r| r.size_bytes as u64).collect();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    // Trashed copies aren't candidates
+    let walker = WalkDir::new(search_root)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != trash::TRASH_DIR);
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if wanted_sizes.contains(&meta.len()) {
+            by_size.entry(meta.len()).or_default().push(entry.into_path());
+        }
+    }
+
+    let mut hashes: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut taken: Vec<PathBuf> = Vec::new();
+
+    for row in &rows {
+        let candidates: Vec<&PathBuf> = by_size
+            .get(&(row.size_bytes as u64))
+            .map(|paths| paths.iter().filter(|p| !taken.contains(p)).collect())
+            .unwrap_or_default();
+
+        let (matches, matched_by): (Vec<&PathBuf>, &str) = match &row.quick_hash {
+            Some(hash) => {
+                let matches = candidates
+                    .into_iter()
+                    .filter(|p| {
+                        hashes
+                            .entry((*p).clone())
+                            .or_insert_with(|| fsops::quick_hash(p).ok())
+                            .as_deref()
+                            == Some(hash.as_str())
+                    })
+                    .collect();
+                (matches, "hash")
+            }
+            None => {
+                let name = Path::new(&row.path).file_name().map(|n| n.to_ascii_lowercase());
+                let matches = candidates
+                    .into_iter()
+                    .filter(|p| p.file_name().map(|n| n.to_ascii_lowercase()) == name)
+                    .collect();
+                (matches, "name_size")
+            }
+        };
+
+        // Same content twice: prefer the copy that kept the original name
+        let original_name = Path::new(&row.path).file_name();
+        let chosen = match matches.as_slice() {
+            [] => None,
+            [only] => Some(*only),
+            many => {
+                let same_name: Vec<&&PathBuf> = many.iter().filter(|p| p.file_name() == original_name).collect();
+                match same_name.as_slice() {
+                    [only] => Some(**only),
+                    _ => {
+                        report.ambiguous.push(row.id);
+                        continue;
+                    }
+                }
+            }
+        };
+        let Some(found) = chosen else {
+            report.not_found.push(row.id);
+            continue;
+        };
+
+        let to = crate::normalize_path(&found.to_string_lossy());
+        repoint(conn, row.id, &to).map_err(|e| e.to_string())?;
+        taken.push(found.clone());
+        report.relinked.push(Relink {
+            id: row.id,
+            from: row.path.clone(),
+            to,
+            matched_by: matched_by.to_string(),
+        });
+    }
+
+    Ok(report)
+}
+
+/// Moves row `id` to `path`. A rescan may already have indexed the file there as a new row;
+/// that duplicate is folded into the original, keeping a star from either.
+pub fn repoint(conn: &Connection, id: i64, path: &str) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE media_items SET starred = MAX(starred, COALESCE((SELECT starred FROM media_items WHERE path = ?1), 0))
+         WHERE id = ?2",
+        params![path, id],
+    )?;
+    tx.execute(
+        "DELETE FROM media_items WHERE path = ?1 AND id != ?2",
+        params![path, id],
+    )?;
+    tx.execute(
+        "UPDATE media_items SET path = ?1 WHERE id = ?2",
+        params![path, id],
+    )?;
+    tx.commit()
+}
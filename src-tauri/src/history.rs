@@ -0,0 +1,153 @@
+use crate::db::{self, changes};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+pub const ENABLED_SETTING: &str = "history_enabled";
+
+/// Shorter videos always start from the beginning
+pub const MIN_RESUME_DURATION_SEC: f64 = 60.0;
+/// Stopping this close to the start isn't worth resuming
+const MIN_RESUME_POSITION_SEC: f64 = 5.0;
+/// Share of a video after which it counts as watched
+const COMPLETED_FRACTION: f64 = 0.95;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Playback {
+    pub position_sec: f64,
+    pub completed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HistoryStatus {
+    pub enabled: bool,
+    pub viewed_items: i64,
+    pub total_views: i64,
+    pub total_watch_time_sec: f64,
+}
+
+/// Recording is on unless turned off in settings.
+pub fn is_enabled(conn: &Connection) -> bool {
+    changes::get_setting(conn, ENABLED_SETTING)
+        .ok()
+        .flatten()
+        .is_none_or(|value| value != "0")
+}
+
+pub fn set_enabled(conn: &Connection, enabled: bool) -> Result<(), String> {
+    changes::set_setting(conn, ENABLED_SETTING, if enabled { "1" } else { "0" }).map_err(|e| e.to_string())
+}
+
+/// Counts one view of `id`, adding `watch_time_sec` of playback for videos.
+/// Returns false, storing nothing, while history is turned off.
+pub fn record_view(conn: &Connection, id: i64, watch_time_sec: f64) -> Result<bool, String> {
+    if !is_enabled(conn) {
+        return Ok(false);
+    }
+    let watched = if watch_time_sec.is_finite() { watch_time_sec.max(0.0) } else { 0.0 };
+    let now = db::unix_now();
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let counted = tx
+        .execute(
+            "INSERT INTO media_views (media_id, first_viewed_at, last_viewed_at, view_count, watch_time_sec)
+             SELECT id, ?2, ?2, 1, ?3 FROM media_items WHERE id = ?1
+             ON CONFLICT(media_id) DO UPDATE SET
+                 last_viewed_at = excluded.last_viewed_at,
+                 view_count = view_count + 1,
+                 watch_time_sec = watch_time_sec + excluded.watch_time_sec",
+            params![id, now, watched],
+        )
+        .map_err(|e| e.to_string())?;
+    if counted > 0 {
+        tx.execute(
+            "INSERT INTO media_view_log (media_id, viewed_at) VALUES (?1, ?2)",
+            params![id, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+pub fn status(conn: &Connection) -> Result<HistoryStatus, String> {
+    let mut status = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(view_count), 0), COALESCE(SUM(watch_time_sec), 0) FROM media_views",
+            [],
+            |row| {
+                Ok(HistoryStatus {
+                    viewed_items: row.get(0)?,
+                    total_views: row.get(1)?,
+                    total_watch_time_sec: row.get(2)?,
+                    ..Default::default()
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    status.enabled = is_enabled(conn);
+    Ok(status)
+}
+
+/// Remembers where playback of `id` stopped. Near the end the video counts as completed and
+/// restarts next time. `None` for images, short videos, or while history is turned off.
+/// `duration_sec` stands in until the scanner has stored the video's own duration.
+pub fn save_playback_position(
+    conn: &Connection,
+    id: i64,
+    position_sec: f64,
+    duration_sec: Option<f64>,
+) -> Result<Option<Playback>, String> {
+    if !is_enabled(conn) || !position_sec.is_finite() {
+        return Ok(None);
+    }
+    let stored: Option<Option<f64>> = conn
+        .query_row(
+            "SELECT duration_sec FROM media_items WHERE id = ?1 AND file_type = 'video'",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    let Some(duration) = stored
+        .or(duration_sec)
+        .filter(|d| d.is_finite() && *d >= MIN_RESUME_DURATION_SEC)
+    else {
+        return Ok(None);
+    };
+
+    let completed = position_sec >= duration * COMPLETED_FRACTION;
+    let position = if completed || position_sec < MIN_RESUME_POSITION_SEC {
+        0.0
+    } else {
+        position_sec
+    };
+    conn.query_row(
+        "INSERT INTO media_playback (media_id, position_sec, completed, played_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(media_id) DO UPDATE SET
+             position_sec = excluded.position_sec,
+             completed = completed OR excluded.completed,
+             played_at = excluded.played_at
+         RETURNING position_sec, completed",
+        params![id, position, completed, db::unix_now()],
+        |row| {
+            Ok(Playback {
+                position_sec: row.get(0)?,
+                completed: row.get(1)?,
+            })
+        },
+    )
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Forgets every view and resume position; returns how many items had history.
+pub fn clear(conn: &Connection) -> Result<usize, String> {
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let cleared = tx.execute("DELETE FROM media_views", []).map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM media_view_log", []).map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM media_playback", []).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cleared)
+}
@@ -1,9 +1,51 @@
-mod db;
-mod scanner;
+mod animation;
+mod backup;
+pub mod db;
+pub mod export;
+mod exclude;
+mod feed_io;
+pub mod formats;
+mod fsops;
+mod health;
+mod history;
+pub mod library;
+mod organize;
+pub mod placeholder;
+pub mod playlist;
+pub mod remap;
+pub mod scanner;
+mod server;
+pub mod shuffle;
+mod stats;
+pub mod transform;
+mod trash;
 
-use tauri::{AppHandle, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
 
-fn normalize_path(path: &str) -> String {
+/// The library every command works on; replaced by `switch_library`.
+struct ActiveLibrary(Mutex<library::Library>);
+
+fn app_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| e.to_string())
+}
+
+fn active_library(app: &AppHandle) -> library::Library {
+    app.state::<ActiveLibrary>().0.lock().unwrap().clone()
+}
+
+/// Database of the active library.
+fn active_db(app: &AppHandle) -> PathBuf {
+    active_library(app).db_path
+}
+
+/// Where the active library keeps thumbnails, trash and backups.
+fn active_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(active_library(app).data_dir(&app_dir(app)?))
+}
+
+pub fn normalize_path(path: &str) -> String {
     // Basic normalization: replace backslashes and trim whitespace
     let mut normalized = path.replace('\\', "/").trim().to_string();
 
@@ -29,46 +71,108 @@ fn normalize_path(path: &str) -> String {
 }
 
 #[tauri::command]
-async fn scan_folder(app: AppHandle, path: String, recursive: bool) -> Result<usize, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
+async fn scan_folder(app: AppHandle, path: String, recursive: Option<bool>) -> Result<usize, String> {
+    let db_path = active_db(&app);
     
     // Normalize path first
     let path = normalize_path(&path);
 
-    // 1. Add to folders table
-    {
-        let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    // 1. Add to folders table; an explicit `recursive` is remembered for later rescans
+    let folder = {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
         db::changes::add_folder(&conn, &path).map_err(|e| e.to_string())?;
-    }
+        let mut folder = db::changes::get_folder(&conn, &path)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("{} is not a library folder", path))?;
+        if let Some(recursive) = recursive {
+            folder.settings.recursive = recursive;
+            db::changes::set_folder_settings(&conn, &path, &folder.settings).map_err(|e| e.to_string())?;
+        }
+        folder
+    };
 
     // 2. Run scan
+    let scan_db_path = db_path.clone();
     let count =
-        tauri::async_runtime::spawn_blocking(move || scanner::scan_directory(&path, &db_path, recursive))
+        tauri::async_runtime::spawn_blocking(move || scanner::rescan_folder(&scan_db_path, &folder))
             .await
             .map_err(|e| e.to_string())??;
 
+    // 3. Placeholders decode every new image, so they follow in the background
+    tauri::async_runtime::spawn_blocking(move || scanner::backfill_placeholders(&db_path));
+
     Ok(count)
 }
 
 #[tauri::command]
 fn get_folders(app: AppHandle) -> Result<Vec<db::Folder>, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::get_folders(&conn).map_err(|e: rusqlite::Error| e.to_string())
 }
 
+#[tauri::command]
+fn get_folder_tree(app: AppHandle) -> Result<Vec<db::FolderNode>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    db::changes::get_folder_tree(&conn).map_err(|e| e.to_string())
+}
+
+/// Returns how many rows new exclude patterns pruned.
+#[tauri::command]
+fn update_folder_settings(app: AppHandle, path: String, settings: db::FolderSettings) -> Result<usize, String> {
+    settings.validate()?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let path = normalize_path(&path);
+    let Some(before) = db::changes::get_folder(&conn, &path).map_err(|e| e.to_string())? else {
+        return Err(format!("{} is not a library folder", path));
+    };
+    db::changes::set_folder_settings(&conn, &path, &settings).map_err(|e| e.to_string())?;
+    if settings.exclude_patterns == before.settings.exclude_patterns {
+        return Ok(0);
+    }
+    let folder = db::Folder { settings, ..before };
+    exclude::prune_folder(&conn, &folder)
+}
+
+#[tauri::command]
+fn get_exclude_patterns(app: AppHandle) -> Result<Vec<String>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(exclude::global_patterns(&conn))
+}
+
+/// Saves the patterns that apply to every folder and prunes what they exclude.
+#[tauri::command]
+fn set_exclude_patterns(app: AppHandle, patterns: Vec<String>) -> Result<usize, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    exclude::set_global_patterns(&conn, &patterns)?;
+    exclude::prune_all(&conn)
+}
+
 #[tauri::command]
 fn remove_folder(app: AppHandle, path: String) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     
     let path = normalize_path(&path);
+    trash::discard_folder(&conn, &path)?;
     db::changes::remove_folder(&conn, &path).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn remap_folder(app: AppHandle, old_root: String, new_root: String) -> Result<remap::RemapReport, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let report = remap::remap_folder(&conn, &old_root, &new_root)?;
+
+    use tauri_plugin_fs::FsExt;
+    let _ = app.fs_scope().allow_directory(normalize_path(&new_root), true);
+    Ok(report)
+}
+
 #[tauri::command]
 fn get_media(
     app: AppHandle,
@@ -76,60 +180,166 @@ fn get_media(
     offset: i64,
     filters: db::FilterOptions,
 ) -> Result<Vec<db::MediaItem>, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    filters.validate()?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::get_media(&conn, limit, offset, filters).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn toggle_star(app: AppHandle, id: i64) -> Result<bool, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::toggle_star(&conn, id).map_err(|e| e.to_string())
 }
 
+/// Counts a view of `id`; `watch_time_sec` is how long a video actually played.
+#[tauri::command]
+fn record_view(app: AppHandle, id: i64, watch_time_sec: Option<f64>) -> Result<bool, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    history::record_view(&conn, id, watch_time_sec.unwrap_or(0.0))
+}
+
+/// Stores where a video was left off; see `history::save_playback_position`.
+#[tauri::command]
+fn save_playback_position(
+    app: AppHandle,
+    id: i64,
+    position_sec: f64,
+    duration_sec: Option<f64>,
+) -> Result<Option<history::Playback>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    history::save_playback_position(&conn, id, position_sec, duration_sec)
+}
+
+#[tauri::command]
+fn get_history_status(app: AppHandle) -> Result<history::HistoryStatus, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    history::status(&conn)
+}
+
+#[tauri::command]
+fn set_history_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    history::set_enabled(&conn, enabled)
+}
+
+#[tauri::command]
+fn clear_history(app: AppHandle) -> Result<usize, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    history::clear(&conn)
+}
+
 #[tauri::command]
 fn clear_favorites(app: AppHandle) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::clear_favorites(&conn).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn get_feeds(app: AppHandle) -> Result<Vec<db::Feed>, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::get_feeds(&conn).map_err(|e: rusqlite::Error| e.to_string())
 }
 
 #[tauri::command]
 fn save_feed(app: AppHandle, feed: db::Feed) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::save_feed(&conn, feed).map_err(|e: rusqlite::Error| e.to_string())
 }
 
 #[tauri::command]
 fn delete_feed(app: AppHandle, id: i64) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::delete_feed(&conn, id).map_err(|e: rusqlite::Error| e.to_string())
 }
 
+#[tauri::command]
+fn export_feeds(app: AppHandle, ids: Option<Vec<i64>>, target_path: String) -> Result<usize, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    feed_io::export_feeds(&conn, ids.as_deref(), Path::new(&target_path))
+}
+
+#[tauri::command]
+fn inspect_feed_import(app: AppHandle, path: String) -> Result<feed_io::FeedImportPreview, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    feed_io::inspect_import(&conn, Path::new(&path))
+}
+
+#[tauri::command]
+fn import_feeds(
+    app: AppHandle,
+    path: String,
+    folder_map: Option<std::collections::HashMap<String, String>>,
+) -> Result<usize, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    feed_io::import_feeds(&conn, Path::new(&path), &folder_map.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn get_library_stats(app: AppHandle) -> Result<stats::LibraryStats, String> {
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        stats::library_stats(&conn).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn check_library_health(app: AppHandle, deep: Option<bool>) -> Result<Vec<health::HealthIssue>, String> {
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        let progress = |p: health::HealthProgress| {
+            let _ = app.emit("health-progress", p);
+        };
+        health::check_library(&conn, deep.unwrap_or(false), &progress)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn relink_media(app: AppHandle, ids: Vec<i64>, search_folder: String) -> Result<health::RelinkReport, String> {
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        health::relink(&conn, &ids, Path::new(&normalize_path(&search_folder)))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn update_media_dimensions(app: AppHandle, id: i64, width: i32, height: i32) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
     db::changes::update_media_dimensions(&conn, id, width, height).map_err(|e| e.to_string())
 }
 
+/// Placeholder from a video frame the frontend has drawn at a small size.
+#[tauri::command]
+fn update_media_poster(app: AppHandle, id: i64, width: u32, height: u32, rgba: Vec<u8>) -> Result<(), String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let placeholder = placeholder::from_rgba(width, height, &rgba)?;
+    db::changes::update_media_placeholder(&conn, id, &placeholder).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn allow_directories(app: AppHandle, paths: Vec<String>) -> Result<(), String> {
     use tauri_plugin_fs::FsExt;
@@ -147,41 +357,413 @@ fn allow_directories(app: AppHandle, paths: Vec<String>) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn export_starred(app: AppHandle, target_path: String) -> Result<usize, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("xcroller.db");
+async fn export_starred(
+    app: AppHandle,
+    target_path: String,
+    collision: Option<export::CollisionPolicy>,
+) -> Result<export::ExportReport, String> {
+    let options = export::ExportOptions {
+        collision: collision.unwrap_or_default(),
+        ..Default::default()
+    };
+    export_media(app, export::ExportSource::Favorites, target_path, options).await
+}
+
+#[tauri::command]
+async fn export_media(
+    app: AppHandle,
+    source: export::ExportSource,
+    target_path: String,
+    options: export::ExportOptions,
+) -> Result<export::ExportReport, String> {
+    let db_path = active_db(&app);
 
-    let items: Vec<String> = {
-        let conn = rusqlite::Connection::open(&db_path).map_err(|e| e.to_string())?;
-        db::changes::get_starred_items(&conn).map_err(|e| e.to_string())?
+    let (items, roots) = {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        let items = export::select_items(&conn, &source)?;
+        let roots: Vec<String> = db::changes::get_folders(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        (items, roots)
     };
 
-    let count = items.len();
-    if count == 0 {
-        return Ok(0);
+    if items.is_empty() {
+        return Ok(export::ExportReport::default());
     }
 
     // Copy files in parallel
     // Need to use blocking task for IO
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let progress = |p: export::ExportProgress| {
+            let _ = app.emit("export-progress", p);
+        };
+        let mut report =
+            export::export_items(&items, &roots, Path::new(&target_path), &options, &progress)?;
+
+        if options.mode == export::ExportMode::Move {
+            let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+            export::record_moves(&conn, &mut report)?;
+        }
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(report)
+}
+
+#[tauri::command]
+async fn export_playlist(
+    app: AppHandle,
+    source: export::ExportSource,
+    target_path: String,
+    options: playlist::PlaylistOptions,
+) -> Result<usize, String> {
+    let db_path = active_db(&app);
+
+    let items = {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        export::select_items(&conn, &source)?
+    };
+
+    playlist::write_playlist(&items, Path::new(&target_path), &options)
+}
+
+#[tauri::command]
+fn delete_media(app: AppHandle, ids: Vec<i64>) -> Result<trash::TrashReport, String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    trash::trash_items(&conn, &data_dir.join("trash"), &ids)
+}
+
+#[tauri::command]
+fn restore_media(app: AppHandle, ids: Vec<i64>) -> Result<trash::TrashReport, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    trash::restore_items(&conn, &ids)
+}
+
+#[tauri::command]
+fn get_trash(app: AppHandle) -> Result<Vec<trash::TrashedItem>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    trash::list_trash(&conn)
+}
+
+#[tauri::command]
+fn empty_trash(app: AppHandle) -> Result<usize, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    trash::empty_trash(&conn, None)
+}
+
+#[tauri::command]
+fn get_trash_retention_days(app: AppHandle) -> Result<i64, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(trash::retention_days(&conn))
+}
+
+#[tauri::command]
+fn set_trash_retention_days(app: AppHandle, days: i64) -> Result<(), String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    db::changes::set_setting(&conn, trash::RETENTION_SETTING, &days.clamp(0, trash::MAX_RETENTION_DAYS).to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_formats(app: AppHandle) -> Result<Vec<formats::FormatInfo>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(formats::list(&conn))
+}
+
+#[tauri::command]
+fn set_enabled_formats(app: AppHandle, ids: Vec<String>) -> Result<(), String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    formats::set_enabled(&conn, &ids)
+}
+
+#[tauri::command]
+fn rename_media(app: AppHandle, id: i64, new_name: String) -> Result<organize::PathChange, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    organize::rename_media(&conn, id, &new_name)
+}
+
+#[tauri::command]
+fn batch_rename_media(
+    app: AppHandle,
+    ids: Vec<i64>,
+    template: String,
+    start: Option<i64>,
+) -> Result<Vec<organize::PathChange>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    organize::batch_rename(&conn, &ids, &template, start.unwrap_or(1))
+}
+
+#[tauri::command]
+fn move_media(
+    app: AppHandle,
+    ids: Vec<i64>,
+    target_folder: String,
+) -> Result<Vec<organize::PathChange>, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let roots: Vec<String> = db::changes::get_folders(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+    organize::move_media(&conn, &ids, &normalize_path(&target_folder), &roots)
+}
+
+#[tauri::command]
+fn get_http_server_status(
+    app: AppHandle,
+    state: tauri::State<server::ServerState>,
+) -> Result<server::ServerStatus, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(state.status(server::load_config(&conn)))
+}
+
+#[tauri::command]
+fn start_http_server(
+    app: AppHandle,
+    state: tauri::State<server::ServerState>,
+    bind_addresses: Vec<String>,
+    port: u16,
+) -> Result<server::ServerStatus, String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let mut config = server::load_config(&conn);
+    config.bind_addresses = bind_addresses;
+    config.port = port;
+
+    state.start(&config, &db_path, &server::thumbnail_dir(&data_dir))?;
+    config.enabled = true;
+    server::save_config(&conn, &config)?;
+    Ok(state.status(config))
+}
+
+#[tauri::command]
+fn stop_http_server(
+    app: AppHandle,
+    state: tauri::State<server::ServerState>,
+) -> Result<server::ServerStatus, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    state.stop();
+    let mut config = server::load_config(&conn);
+    config.enabled = false;
+    server::save_config(&conn, &config)?;
+    Ok(state.status(config))
+}
+
+/// Issues a new token, which signs out every connected browser.
+#[tauri::command]
+fn regenerate_http_token(
+    app: AppHandle,
+    state: tauri::State<server::ServerState>,
+) -> Result<server::ServerStatus, String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let mut config = server::load_config(&conn);
+    config.token = server::generate_token();
+    server::save_config(&conn, &config)?;
+    if state.status(config.clone()).running {
+        state.start(&config, &db_path, &server::thumbnail_dir(&data_dir))?;
+    }
+    Ok(state.status(config))
+}
+
+#[tauri::command]
+fn create_backup(app: AppHandle) -> Result<backup::BackupInfo, String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    let dir = backup::backup_dir(&data_dir);
+    let info = backup::create_backup(&conn, &dir)?;
+    backup::rotate(&dir, backup::schedule(&conn).keep);
+    Ok(info)
+}
+
+#[tauri::command]
+fn list_backups(app: AppHandle) -> Result<Vec<backup::BackupInfo>, String> {
+    Ok(backup::list_backups(&backup::backup_dir(&active_data_dir(&app)?)))
+}
+
+#[tauri::command]
+async fn restore_backup(app: AppHandle, path: String) -> Result<(), String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
     tauri::async_runtime::spawn_blocking(move || {
-        use rayon::prelude::*;
-        use std::fs;
-        use std::path::Path;
-
-        items.par_iter().for_each(|src_path_str| {
-            let src_path = Path::new(src_path_str);
-            if let Some(file_name) = src_path.file_name() {
-                let dest_path = Path::new(&target_path).join(file_name);
-                if let Err(e) = fs::copy(src_path, dest_path) {
-                    eprintln!("Failed to copy {:?}: {}", src_path, e);
-                }
-            }
-        });
+        let mut conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        backup::restore_backup(&mut conn, &backup::backup_dir(&data_dir), Path::new(&path))
     })
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| e.to_string())?
+}
 
-    Ok(count)
+#[tauri::command]
+fn get_backup_schedule(app: AppHandle) -> Result<backup::BackupSchedule, String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(backup::schedule(&conn))
+}
+
+#[tauri::command]
+fn set_backup_schedule(app: AppHandle, schedule: backup::BackupSchedule) -> Result<(), String> {
+    let db_path = active_db(&app);
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    backup::set_schedule(&conn, &schedule)
+}
+
+#[tauri::command]
+async fn check_database_integrity(app: AppHandle) -> Result<Vec<String>, String> {
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        backup::integrity_check(&conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn optimize_database(app: AppHandle) -> Result<backup::OptimizeReport, String> {
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        backup::optimize(&conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn clean_orphans(app: AppHandle) -> Result<backup::OrphanReport, String> {
+    let data_dir = active_data_dir(&app)?;
+    let db_path = active_db(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+        backup::clean_orphans(&conn, &server::thumbnail_dir(&data_dir))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Makes `library` the one commands work on and runs its startup chores: migrations,
+/// asset access for its folders, metadata backfills, its LAN server and trash purging.
+fn activate_library(app: &AppHandle, library: library::Library) -> Result<(), String> {
+    let data_dir = library.data_dir(&app_dir(app)?);
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    let db_path = library.db_path.clone();
+    // Don't quietly start an empty database when the drive holding it isn't mounted
+    if library.external && !db_path.is_file() {
+        return Err(format!("{} is not available", db_path.display()));
+    }
+
+    // Init DB
+    let conn = db::open(&db_path).map_err(|e| e.to_string())?;
+    db::init_schema(&conn).map_err(|e| format!("Can't open {}: {}", db_path.display(), e))?;
+    *app.state::<ActiveLibrary>().0.lock().unwrap() = library;
+
+    // Allow existing folders in fs scope for asset protocol
+    if let Ok(folders) = db::changes::get_folders(&conn) {
+        use tauri_plugin_fs::FsExt;
+        for folder in folders {
+            let normalized = normalize_path(&folder.path);
+            let _ = app.fs_scope().allow_directory(&normalized, true);
+            if normalized != folder.path {
+                let _ = app.fs_scope().allow_directory(&folder.path, true);
+            }
+        }
+    }
+
+    // Backfill missing metadata in background
+    let db_path_clone = db_path.clone();
+    tauri::async_runtime::spawn(async move {
+        let _ = scanner::backfill_metadata(&db_path_clone);
+        let _ = scanner::backfill_placeholders(&db_path_clone);
+    });
+
+    // The server only ever serves the active library
+    let server_state = app.state::<server::ServerState>();
+    server_state.stop();
+    server::start_if_enabled(&server_state, &db_path, &data_dir);
+
+    // Drop trashed items past their retention period
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = trash::purge_expired(&conn) {
+            eprintln!("Failed to purge trash: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_libraries(app: AppHandle) -> Result<library::Registry, String> {
+    let mut registry = library::Registry::load(&app_dir(&app)?);
+    registry.active = active_library(&app).id;
+    Ok(registry)
+}
+
+#[tauri::command]
+fn create_library(app: AppHandle, name: String) -> Result<library::Library, String> {
+    library::create(&app_dir(&app)?, &name)
+}
+
+/// Registers an existing database, e.g. one stored on the media drive.
+#[tauri::command]
+fn open_library_file(app: AppHandle, path: String, name: Option<String>) -> Result<library::Library, String> {
+    library::open_existing(&app_dir(&app)?, Path::new(&path), name.as_deref())
+}
+
+#[tauri::command]
+fn rename_library(app: AppHandle, id: String, name: String) -> Result<library::Library, String> {
+    let renamed = library::rename(&app_dir(&app)?, &id, &name)?;
+    let state = app.state::<ActiveLibrary>();
+    let mut active = state.0.lock().unwrap();
+    if active.id == renamed.id {
+        *active = renamed.clone();
+    }
+    Ok(renamed)
+}
+
+#[tauri::command]
+async fn switch_library(app: AppHandle, id: String) -> Result<library::Library, String> {
+    let app_dir = app_dir(&app)?;
+    let registry = library::Registry::load(&app_dir);
+    let library = registry
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("No library with id {}", id))?;
+    // Opened before it's remembered, so a broken database doesn't become the startup library
+    let activate_app = app.clone();
+    let activated = library.clone();
+    tauri::async_runtime::spawn_blocking(move || activate_library(&activate_app, activated))
+        .await
+        .map_err(|e| e.to_string())??;
+    library::switch(&app_dir, &library.id)
+}
+
+#[tauri::command]
+fn delete_library(app: AppHandle, id: String) -> Result<(), String> {
+    if active_library(&app).id == id {
+        return Err("Switch to another library before deleting this one".to_string());
+    }
+    library::delete(&app_dir(&app)?, &id)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -191,57 +773,116 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
+        .manage(server::ServerState::default())
         .setup(|app| {
             let app_dir = app
                 .path()
                 .app_data_dir()
                 .expect("failed to get app data dir");
             std::fs::create_dir_all(&app_dir).expect("failed to create app data dir");
-            let db_path = app_dir.join("xcroller.db");
-
-            // Init DB
-            let conn = rusqlite::Connection::open(&db_path).expect("failed to open db");
-            conn.execute(db::SCHEMA_MEDIA, [])
-                .expect("failed to create media table");
-            conn.execute(db::SCHEMA_FOLDERS, [])
-                .expect("failed to create folders table");
-            conn.execute(db::SCHEMA_FEEDS, [])
-                .expect("failed to create feeds table");
-            conn.execute(db::SCHEMA_INDICES, [])
-                .expect("failed to create indices");
-
-            // Allow existing folders in fs scope for asset protocol
-            if let Ok(folders) = db::changes::get_folders(&conn) {
-                use tauri_plugin_fs::FsExt;
-                for folder in folders {
-                    let normalized = normalize_path(&folder.path);
-                    let _ = app.fs_scope().allow_directory(&normalized, true);
-                    if normalized != folder.path {
-                        let _ = app.fs_scope().allow_directory(&folder.path, true);
-                    }
+
+            let registry = library::Registry::load(&app_dir);
+            let library = registry.active().clone();
+            app.manage(ActiveLibrary(Mutex::new(library.clone())));
+            if let Err(e) = activate_library(app.handle(), library.clone()) {
+                if library.id == library::DEFAULT_ID {
+                    return Err(e.into());
                 }
+                eprintln!("Failed to open library {}: {}; using the default one", library.name, e);
+                let fallback = registry.get(library::DEFAULT_ID).cloned().expect("registry has a default");
+                activate_library(app.handle(), fallback)?;
             }
 
-            // Backfill missing metadata in background
-            let db_path_clone = db_path.clone();
-            tauri::async_runtime::spawn(async move {
-                let _ = scanner::backfill_metadata(&db_path_clone);
+            // Scheduled backups; checked hourly so long-running sessions get them too
+            let backup_app = app.handle().clone();
+            std::thread::spawn(move || loop {
+                if let Ok(data_dir) = active_data_dir(&backup_app) {
+                    if let Ok(conn) = db::open(active_db(&backup_app)) {
+                        if let Err(e) = backup::run_scheduled(&conn, &backup::backup_dir(&data_dir)) {
+                            eprintln!("Scheduled backup failed: {}", e);
+                        }
+                    }
+                }
+                std::thread::sleep(std::time::Duration::from_secs(60 * 60));
+            });
+
+            // Folders with an auto-rescan interval; the frontend refreshes on "folders-rescanned"
+            let rescan_app = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(60));
+                let rescan_db = active_db(&rescan_app);
+                match scanner::rescan_due(&rescan_db) {
+                    Ok(counts) if !counts.is_empty() => {
+                        let _ = scanner::backfill_placeholders(&rescan_db);
+                        let _ = rescan_app.emit("folders-rescanned", &counts);
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Auto-rescan failed: {}", e),
+                }
             });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            get_libraries,
+            create_library,
+            open_library_file,
+            rename_library,
+            switch_library,
+            delete_library,
             scan_folder,
             get_folders,
+            get_folder_tree,
+            update_folder_settings,
+            get_exclude_patterns,
+            set_exclude_patterns,
             remove_folder,
+            remap_folder,
             get_media,
             toggle_star,
+            record_view,
+            save_playback_position,
+            get_history_status,
+            set_history_enabled,
+            clear_history,
             clear_favorites,
             export_starred,
+            export_media,
+            export_playlist,
+            delete_media,
+            restore_media,
+            get_trash,
+            empty_trash,
+            get_trash_retention_days,
+            set_trash_retention_days,
+            get_formats,
+            set_enabled_formats,
+            rename_media,
+            batch_rename_media,
+            move_media,
+            get_http_server_status,
+            start_http_server,
+            stop_http_server,
+            regenerate_http_token,
+            create_backup,
+            list_backups,
+            restore_backup,
+            get_backup_schedule,
+            set_backup_schedule,
+            check_database_integrity,
+            optimize_database,
+            clean_orphans,
+            get_library_stats,
+            check_library_health,
+            relink_media,
             update_media_dimensions,
+            update_media_poster,
             get_feeds,
             save_feed,
             delete_feed,
+            export_feeds,
+            inspect_feed_import,
+            import_feeds,
             allow_directories
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const REGISTRY_FILE: &str = "libraries.json";
+const DB_FILE: &str = "xcroller.db";
+/// The library that existed before there were several; it lives directly in the app data dir
+pub const DEFAULT_ID: &str = "default";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Library {
+    pub id: String,
+    pub name: String,
+    pub db_path: PathBuf,
+    /// Opened from a database elsewhere (e.g. on the media drive); deleting the library leaves it be
+    #[serde(default)]
+    pub external: bool,
+}
+
+impl Library {
+    /// Thumbnails, trash and backups. Caches stay on this machine even for external databases.
+    pub fn data_dir(&self, app_dir: &Path) -> PathBuf {
+        if self.id == DEFAULT_ID {
+            app_dir.to_path_buf()
+        } else {
+            app_dir.join("libraries").join(&self.id)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Registry {
+    pub active: String,
+    pub libraries: Vec<Library>,
+}
+
+impl Registry {
+    /// The saved registry, or just the default library on first run.
+    pub fn load(app_dir: &Path) -> Registry {
+        let mut registry = fs::read_to_string(app_dir.join(REGISTRY_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Registry>(&json).ok())
+            .unwrap_or(Registry {
+                active: DEFAULT_ID.to_string(),
+                libraries: Vec::new(),
+            });
+        if !registry.libraries.iter().any(|l| l.id == DEFAULT_ID) {
+            registry.libraries.insert(
+                0,
+                Library {
+                    id: DEFAULT_ID.to_string(),
+                    name: "Default".to_string(),
+                    db_path: app_dir.join(DB_FILE),
+                    external: false,
+                },
+            );
+        }
+        if registry.get(&registry.active).is_none() {
+            registry.active = DEFAULT_ID.to_string();
+        }
+        registry
+    }
+
+    fn save(&self, app_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(app_dir).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        // Written aside first so a crash can't leave a half-written registry
+        let tmp = app_dir.join(format!("{}.tmp", REGISTRY_FILE));
+        fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, app_dir.join(REGISTRY_FILE)).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Library> {
+        self.libraries.iter().find(|l| l.id == id)
+    }
+
+    pub fn active(&self) -> &Library {
+        self.get(&self.active).unwrap_or(&self.libraries[0])
+    }
+
+    /// Looks a library up by id, or failing that by name (for the CLI).
+    pub fn find(&self, id_or_name: &str) -> Option<&Library> {
+        self.get(id_or_name)
+            .or_else(|| self.libraries.iter().find(|l| l.name.eq_ignore_ascii_case(id_or_name)))
+    }
+
+    fn check_name(&self, name: &str, except: Option<&str>) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Give the library a name".to_string());
+        }
+        if self
+            .libraries
+            .iter()
+            .any(|l| Some(l.id.as_str()) != except && l.name.eq_ignore_ascii_case(name))
+        {
+            return Err(format!("There is already a library called \"{}\"", name));
+        }
+        Ok(name.to_string())
+    }
+
+    /// A folder-safe id derived from `name`, unique within the registry.
+    fn new_id(&self, name: &str) -> String {
+        let slug: String = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+        let slug = if slug.is_empty() || slug == DEFAULT_ID { "library".to_string() } else { slug };
+        let mut id = slug.clone();
+        let mut n = 2;
+        while self.get(&id).is_some() {
+            id = format!("{}-{}", slug, n);
+            n += 1;
+        }
+        id
+    }
+}
+
+/// Registers a new, empty library. It isn't switched to.
+pub fn create(app_dir: &Path, name: &str) -> Result<Library, String> {
+    let mut registry = Registry::load(app_dir);
+    let name = registry.check_name(name, None)?;
+    let id = registry.new_id(&name);
+    let mut library = Library {
+        id,
+        name,
+        db_path: PathBuf::new(),
+        external: false,
+    };
+    library.db_path = library.data_dir(app_dir).join(DB_FILE);
+    fs::create_dir_all(library.data_dir(app_dir)).map_err(|e| e.to_string())?;
+    registry.libraries.push(library.clone());
+    registry.save(app_dir)?;
+    Ok(library)
+}
+
+/// Registers an existing database file, e.g. one kept on the drive with the media.
+/// Opening the same file twice returns the library already registered for it.
+pub fn open_existing(app_dir: &Path, db_path: &Path, name: Option<&str>) -> Result<Library, String> {
+    if !db_path.is_file() {
+        return Err(format!("{} is not a file", db_path.display()));
+    }
+    let db_path = fs::canonicalize(db_path).map_err(|e| e.to_string())?;
+    let mut registry = Registry::load(app_dir);
+    if let Some(existing) = registry
+        .libraries
+        .iter()
+        .find(|l| fs::canonicalize(&l.db_path).is_ok_and(|p| p == db_path))
+    {
+        return Ok(existing.clone());
+    }
+
+    // Named after the folder holding the database unless told otherwise
+    let fallback = db_path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Library".to_string());
+    let name = registry.check_name(name.unwrap_or(&fallback), None)?;
+    let library = Library {
+        id: registry.new_id(&name),
+        name,
+        db_path,
+        external: true,
+    };
+    registry.libraries.push(library.clone());
+    registry.save(app_dir)?;
+    Ok(library)
+}
+
+pub fn rename(app_dir: &Path, id: &str, name: &str) -> Result<Library, String> {
+    let mut registry = Registry::load(app_dir);
+    let name = registry.check_name(name, Some(id))?;
+    let library = registry
+        .libraries
+        .iter_mut()
+        .find(|l| l.id == id)
+        .ok_or_else(|| format!("No library with id {}", id))?;
+    library.name = name;
+    let library = library.clone();
+    registry.save(app_dir)?;
+    Ok(library)
+}
+
+/// Remembers `id` as the library to open.
+pub fn switch(app_dir: &Path, id: &str) -> Result<Library, String> {
+    let mut registry = Registry::load(app_dir);
+    let library = registry
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("No library with id {}", id))?;
+    registry.active = library.id.clone();
+    registry.save(app_dir)?;
+    Ok(library)
+}
+
+/// Unregisters a library and removes its data: the database too, unless it was opened
+/// from elsewhere. The default and the active library can't be deleted.
+pub fn delete(app_dir: &Path, id: &str) -> Result<(), String> {
+    let mut registry = Registry::load(app_dir);
+    if id == DEFAULT_ID {
+        return Err("The default library can't be deleted".to_string());
+    }
+    if id == registry.active {
+        return Err("Switch to another library before deleting this one".to_string());
+    }
+    let library = registry
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("No library with id {}", id))?;
+    registry.libraries.retain(|l| l.id != id);
+    registry.save(app_dir)?;
+
+    let data_dir = library.data_dir(app_dir);
+    if data_dir.is_dir() {
+        fs::remove_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
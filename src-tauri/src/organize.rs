@@ -0,0 +1,298 @@
+use crate::{fsops, normalize_path};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PathChange {
+    pub id: i64,
+    pub from: String,
+    pub to: String,
+}
+
+struct Source {
+    id: i64,
+    path: PathBuf,
+    created_at: i64,
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+fn load_source(conn: &Connection, id: i64) -> Result<Source, String> {
+    conn.query_row(
+        "SELECT path, created_at, width, height FROM media_items WHERE id = ?1 AND trashed_at IS NULL",
+        params![id],
+        |row| {
+            Ok(Source {
+                id,
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                created_at: row.get(1)?,
+                width: row.get(2)?,
+                height: row.get(3)?,
+            })
+        },
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Media item {} not found", id),
+        e => e.to_string(),
+    })
+}
+
+fn validate_file_name(name: &str) -> Result<(), String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() || trimmed == "." || trimmed == ".." {
+        return Err(format!("\"{}\" is not a valid file name", name));
+    }
+    if name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+        return Err(format!("\"{}\" contains characters that aren't allowed in file names", name));
+    }
+    Ok(())
+}
+
+/// Renames one file in place, keeping its row (and with it the star and metadata).
+pub fn rename_media(conn: &Connection, id: i64, new_name: &str) -> Result<PathChange, String> {
+    validate_file_name(new_name)?;
+    let source = load_source(conn, id)?;
+    let dest = source
+        .path
+        .parent()
+        .map(|p| p.join(new_name))
+        .unwrap_or_else(|| PathBuf::from(new_name));
+
+    apply_changes(conn, &[(source.id, source.path, dest)]).map(|mut c| c.remove(0))
+}
+
+/// Moves files into `target_folder`, which must sit inside one of the library `roots`.
+pub fn move_media(
+    conn: &Connection,
+    ids: &[i64],
+    target_folder: &str,
+    roots: &[String],
+) -> Result<Vec<PathChange>, String> {
+    let target = target_folder.replace('\\', "/");
+    let target = target.trim_end_matches('/');
+    let inside_library = roots.iter().any(|r| {
+        let r = r.replace('\\', "/");
+        let r = r.trim_end_matches('/');
+        target == r || target.starts_with(&format!("{}/", r))
+    });
+    if !inside_library {
+        return Err(format!(
+            "{} is not inside a library folder; moved items would disappear from every feed",
+            target_folder
+        ));
+    }
+    if !Path::new(target_folder).is_dir() {
+        return Err(format!("{} is not a folder", target_folder));
+    }
+
+    let mut moves = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let source = load_source(conn, id)?;
+        let Some(file_name) = source.path.file_name() else {
+            return Err(format!("{} has no file name", source.path.display()));
+        };
+        let dest = Path::new(target_folder).join(file_name);
+        moves.push((source.id, source.path, dest));
+    }
+    apply_changes(conn, &moves)
+}
+
+/// Renames `ids` (in that order) from a template such as "{date}_{counter:3}.{ext}".
+///
+/// Tokens: `{name}` original stem, `{ext}`, `{date}` (YYYY-MM-DD of `created_at`), `{id}`,
+/// `{width}`, `{height}` and `{counter}`, optionally zero-padded as `{counter:N}`.
+pub fn batch_rename(
+    conn: &Connection,
+    ids: &[i64],
+    template: &str,
+    start: i64,
+) -> Result<Vec<PathChange>, String> {
+    let mut renames = Vec::with_capacity(ids.len());
+    for (i, &id) in ids.iter().enumerate() {
+        let source = load_source(conn, id)?;
+        let name = render_template(template, &source, start + i as i64)?;
+        validate_file_name(&name)?;
+        let dest = source
+            .path
+            .parent()
+            .map(|p| p.join(&name))
+            .unwrap_or_else(|| PathBuf::from(&name));
+        renames.push((source.id, source.path, dest));
+    }
+    apply_changes(conn, &renames)
+}
+
+fn render_template(template: &str, source: &Source, counter: i64) -> Result<String, String> {
+    let stem = source
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = source
+        .path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            return Err(format!("Unclosed token in template \"{}\"", template));
+        };
+        let token = &rest[open + 1..open + close];
+        let (key, arg) = token.split_once(':').unwrap_or((token, ""));
+        let value = match key {
+            "name" => stem.clone(),
+            "ext" => ext.clone(),
+            "date" => format_date(source.created_at),
+            "id" => source.id.to_string(),
+            "width" => source.width.map(|w| w.to_string()).unwrap_or_default(),
+            "height" => source.height.map(|h| h.to_string()).unwrap_or_default(),
+            "counter" => {
+                let width: usize = if arg.is_empty() {
+                    0
+                } else {
+                    arg.parse()
+                        .map_err(|_| format!("Invalid counter width \"{}\"", arg))?
+                };
+                format!("{:0width$}", counter, width = width)
+            }
+            _ => return Err(format!("Unknown template token {{{}}}", token)),
+        };
+        out.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// "YYYY-MM-DD" (UTC) for a unix timestamp.
+fn format_date(timestamp: i64) -> String {
+    // Howard Hinnant's days-to-civil algorithm
+    let days = timestamp.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Performs all file operations or none, then repoints the rows in one transaction.
+/// Targets may be paths other entries are vacating (e.g. renumbering), but never foreign files.
+fn apply_changes(conn: &Connection, changes: &[(i64, PathBuf, PathBuf)]) -> Result<Vec<PathChange>, String> {
+    // Sources by case-folded name: only those can be the same file as a target
+    let mut sources: HashMap<String, Vec<&Path>> = HashMap::new();
+    for (_, from, _) in changes {
+        sources
+            .entry(from.to_string_lossy().to_lowercase())
+            .or_default()
+            .push(from);
+    }
+    let mut targets: HashSet<String> = HashSet::new();
+
+    for (_, from, to) in changes {
+        let key = to.to_string_lossy().to_lowercase();
+        if !targets.insert(key.clone()) {
+            return Err(format!("More than one file would be named {}", to.display()));
+        }
+        // A taken target is fine only if it is a file being moved away, including a case-only
+        // rename that sees itself on a case-insensitive filesystem
+        let vacated = || {
+            sources
+                .get(&key)
+                .is_some_and(|paths| paths.iter().any(|p| fsops::same_file(p, to)))
+        };
+        if to.exists() && !vacated() {
+            return Err(format!("{} already exists", to.display()));
+        }
+        if !from.exists() {
+            return Err(format!("{} does not exist", from.display()));
+        }
+    }
+
+    // Two passes through temporary names, so chains like a->b, b->c never clobber each other
+    let staged: Vec<PathBuf> = changes
+        .iter()
+        .map(|(id, _, to)| {
+            to.with_file_name(format!(
+                ".xcroller-move-{}-{}",
+                id,
+                to.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+            ))
+        })
+        .collect();
+
+    let mut done_stage = 0;
+    let mut failure = None;
+    for ((_, from, _), tmp) in changes.iter().zip(&staged) {
+        if let Err(e) = fsops::move_file(from, tmp) {
+            failure = Some(format!("Failed to move {}: {}", from.display(), e));
+            break;
+        }
+        done_stage += 1;
+    }
+
+    let mut done_final = 0;
+    if failure.is_none() {
+        for ((_, _, to), tmp) in changes.iter().zip(&staged) {
+            if let Err(e) = fsops::move_file(tmp, to) {
+                failure = Some(format!("Failed to move to {}: {}", to.display(), e));
+                break;
+            }
+            done_final += 1;
+        }
+    }
+
+    if failure.is_none() {
+        if let Err(e) = update_paths(conn, changes) {
+            failure = Some(e.to_string());
+        }
+    }
+
+    if let Some(message) = failure {
+        // Unwind in reverse so every file ends up where it started
+        for ((_, _, to), tmp) in changes.iter().zip(&staged).take(done_final).rev() {
+            let _ = fsops::move_file(to, tmp);
+        }
+        for ((_, from, _), tmp) in changes.iter().zip(&staged).take(done_stage).rev() {
+            let _ = fsops::move_file(tmp, from);
+        }
+        return Err(message);
+    }
+
+    Ok(changes
+        .iter()
+        .map(|(id, from, to)| PathChange {
+            id: *id,
+            from: from.to_string_lossy().to_string(),
+            to: normalize_path(&to.to_string_lossy()),
+        })
+        .collect())
+}
+
+fn update_paths(conn: &Connection, changes: &[(i64, PathBuf, PathBuf)]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    // `path` is UNIQUE, so park every row on a placeholder before assigning the final paths
+    for (id, _, _) in changes {
+        tx.execute(
+            "UPDATE media_items SET path = ?1 WHERE id = ?2",
+            params![format!(":moving:{}", id), id],
+        )?;
+    }
+    for (id, _, to) in changes {
+        tx.execute(
+            "UPDATE media_items SET path = ?1 WHERE id = ?2",
+            params![normalize_path(&to.to_string_lossy()), id],
+        )?;
+    }
+    tx.commit()
+}
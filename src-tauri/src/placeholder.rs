@@ -0,0 +1,117 @@
+use crate::db;
+use crate::formats::Format;
+use image::codecs::jpeg::JpegDecoder;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// Placeholders are blurry by design; a tiny sample is all the math needs
+const SAMPLE_EDGE: u32 = 32;
+
+/// What a tile shows before its media has loaded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub blurhash: String,
+    pub dominant_color: String, // "#rrggbb"
+    pub average_color: String,
+    /// CIELAB of the dominant color
+    pub dominant_lab: [f64; 3],
+    /// Mean CIELAB chroma of the sample; near 0 for grayscale
+    pub colorfulness: f64,
+}
+
+/// Samples a still image. JPEGs are decoded at reduced scale, so this stays cheap for large photos.
+pub fn from_file(path: &Path, format: &Format) -> Result<Placeholder, String> {
+    if !format.decodable {
+        return Err(format!("Can't decode {} files", format.id));
+    }
+    let image = if format.id == "jpeg" {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut decoder = JpegDecoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+        decoder
+            .scale(SAMPLE_EDGE as u16, SAMPLE_EDGE as u16)
+            .map_err(|e| e.to_string())?;
+        DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?
+    } else {
+        image::open(path).map_err(|e| e.to_string())?
+    };
+
+    let sample = if image.width() > SAMPLE_EDGE || image.height() > SAMPLE_EDGE {
+        image.thumbnail(SAMPLE_EDGE, SAMPLE_EDGE)
+    } else {
+        image
+    };
+    let (width, height) = sample.dimensions();
+    from_rgba(width, height, sample.to_rgba8().as_raw())
+}
+
+/// Builds a placeholder from raw RGBA pixels, e.g. a video frame sampled by the frontend.
+pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Placeholder, String> {
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(4));
+    if width == 0 || height == 0 || expected != Some(rgba.len()) {
+        return Err("Pixel data doesn't match the given size".to_string());
+    }
+
+    // More detail along the long edge
+    let (x, y) = if width >= height { (4, 3) } else { (3, 4) };
+    let blurhash = blurhash::encode(x, y, width, height, rgba).map_err(|e| e.to_string())?;
+
+    let (average, dominant) = colors(rgba);
+    Ok(Placeholder {
+        blurhash,
+        dominant_color: hex(dominant),
+        average_color: hex(average),
+        dominant_lab: db::srgb_to_lab(dominant),
+        colorfulness: colorfulness(rgba),
+    })
+}
+
+/// Mean of all opaque pixels, and the mean of the most common 4-bit-per-channel bucket.
+fn colors(rgba: &[u8]) -> ([u8; 3], [u8; 3]) {
+    let mut total = [0u64; 4]; // r, g, b, count
+    let mut buckets = vec![[0u64; 4]; 4096];
+    for px in rgba.chunks_exact(4) {
+        if px[3] < 128 {
+            continue;
+        }
+        let bucket = &mut buckets[((px[0] as usize >> 4) << 8) | ((px[1] as usize >> 4) << 4) | (px[2] as usize >> 4)];
+        for i in 0..3 {
+            total[i] += px[i] as u64;
+            bucket[i] += px[i] as u64;
+        }
+        total[3] += 1;
+        bucket[3] += 1;
+    }
+
+    let mean = |s: &[u64; 4]| -> [u8; 3] {
+        if s[3] == 0 {
+            return [0, 0, 0];
+        }
+        [(s[0] / s[3]) as u8, (s[1] / s[3]) as u8, (s[2] / s[3]) as u8]
+    };
+    let dominant = buckets.iter().max_by_key(|b| b[3]).expect("4096 buckets");
+    (mean(&total), mean(dominant))
+}
+
+fn colorfulness(rgba: &[u8]) -> f64 {
+    let chromas: Vec<f64> = rgba
+        .chunks_exact(4)
+        .filter(|px| px[3] >= 128)
+        .map(|px| {
+            let [_, a, b] = db::srgb_to_lab([px[0], px[1], px[2]]);
+            a.hypot(b)
+        })
+        .collect();
+    if chromas.is_empty() {
+        return 0.0;
+    }
+    chromas.iter().sum::<f64>() / chromas.len() as f64
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
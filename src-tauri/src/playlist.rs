@@ -0,0 +1,161 @@
+use crate::db::MediaItem;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistFormat {
+    M3u8,
+    Xspf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistPaths {
+    #[default]
+    Absolute,
+    Relative, // To the playlist's folder; items on another drive stay absolute
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaylistOptions {
+    pub format: PlaylistFormat,
+    #[serde(default)]
+    pub paths: PlaylistPaths,
+    pub title: Option<String>,
+}
+
+/// Writes `items` to `target` in order and returns the number of entries.
+pub fn write_playlist(items: &[MediaItem], target: &Path, options: &PlaylistOptions) -> Result<usize, String> {
+    let base = target.parent().unwrap_or(Path::new(""));
+    let title = options.title.clone().unwrap_or_else(|| {
+        target
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    let contents = match options.format {
+        PlaylistFormat::M3u8 => m3u8(items, base, options.paths, &title),
+        PlaylistFormat::Xspf => xspf(items, base, options.paths, &title),
+    };
+
+    fs::write(target, contents).map_err(|e| e.to_string())?;
+    Ok(items.len())
+}
+
+fn m3u8(items: &[MediaItem], base: &Path, paths: PlaylistPaths, title: &str) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    let _ = writeln!(out, "#PLAYLIST:{}", title);
+    for item in items {
+        // -1 is the conventional "unknown length"
+        let duration = item.duration_sec.map(|d| d.round() as i64).unwrap_or(-1);
+        let _ = writeln!(out, "#EXTINF:{},{}", duration, file_name(&item.path));
+        let _ = writeln!(out, "{}", entry_path(&item.path, base, paths));
+    }
+    out
+}
+
+fn xspf(items: &[MediaItem], base: &Path, paths: PlaylistPaths, title: &str) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    let _ = writeln!(out, "  <title>{}</title>", xml_escape(title));
+    out.push_str("  <trackList>\n");
+    for item in items {
+        let location = match relative_path(&item.path, base).filter(|_| paths == PlaylistPaths::Relative) {
+            Some(rel) => uri_encode(&rel),
+            None => file_uri(&item.path),
+        };
+        out.push_str("    <track>\n");
+        let _ = writeln!(out, "      <location>{}</location>", xml_escape(&location));
+        let _ = writeln!(out, "      <title>{}</title>", xml_escape(&file_name(&item.path)));
+        if let Some(d) = item.duration_sec {
+            // XSPF durations are in milliseconds
+            let _ = writeln!(out, "      <duration>{}</duration>", (d * 1000.0).round() as i64);
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn entry_path(path: &str, base: &Path, paths: PlaylistPaths) -> String {
+    match paths {
+        PlaylistPaths::Relative => relative_path(path, base).unwrap_or_else(|| path.to_string()),
+        PlaylistPaths::Absolute => path.to_string(),
+    }
+}
+
+/// `path` relative to `base` with forward slashes, or None when they share no root (e.g. another drive).
+fn relative_path(path: &str, base: &Path) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let base = base.to_string_lossy().replace('\\', "/");
+
+    // "/" on Unix, the drive letter on Windows (compared case-insensitively)
+    let root = |p: &str| match p.split('/').next() {
+        Some(first) if first.ends_with(':') => Some(first.to_uppercase()),
+        _ if p.starts_with('/') => Some("/".to_string()),
+        _ => None,
+    };
+    if root(&path).is_none() || root(&path) != root(&base) {
+        return None;
+    }
+
+    // The drive letter was already matched above
+    let skip = if path.starts_with('/') { 0 } else { 1 };
+    let path_parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).skip(skip).collect();
+    let base_parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).skip(skip).collect();
+
+    let common = path_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<&str> = vec![".."; base_parts.len() - common];
+    parts.extend(&path_parts[common..]);
+    Some(parts.join("/"))
+}
+
+fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", uri_encode(&path))
+    } else {
+        // Windows: "C:/x" -> "file:///C:/x"
+        format!("file:///{}", uri_encode(&path))
+    }
+}
+
+fn uri_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                out.push(b as char)
+            }
+            _ => {
+                let _ = write!(out, "%{:02X}", b);
+            }
+        }
+    }
+    out
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
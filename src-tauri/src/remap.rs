@@ -0,0 +1,126 @@
+use crate::db::changes;
+use crate::normalize_path;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RemapReport {
+    pub folders: usize,
+    pub items: usize,
+    pub feeds: usize,
+}
+
+/// `path` with `old_root` swapped for `new_root`, if it lies inside `old_root`.
+fn rebase(path: &str, old_root: &str, new_root: &str) -> Option<String> {
+    if path == old_root {
+        return Some(new_root.to_string());
+    }
+    path.strip_prefix(old_root)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(|rest| format!("{}/{}", new_root, rest))
+}
+
+/// Rewrites a JSON array of paths; `None` when nothing in it moved.
+fn rebase_json_paths(json: &str, old_root: &str, new_root: &str) -> Option<String> {
+    let paths: Vec<String> = serde_json::from_str(json).ok()?;
+    let mut changed = false;
+    let rebased: Vec<String> = paths
+        .into_iter()
+        .map(|p| match rebase(&normalize_path(&p), old_root, new_root) {
+            Some(moved) => {
+                changed = true;
+                moved
+            }
+            None => p,
+        })
+        .collect();
+    changed.then(|| serde_json::to_string(&rebased).ok()).flatten()
+}
+
+/// Points the library root `old_root` (and any roots nested in it) at `new_root`, e.g. after
+/// the drive got a new letter. Rows are updated in place, so stars, trash state and metadata stay.
+/// Feeds that name folders under the old root follow along.
+pub fn remap_folder(conn: &Connection, old_root: &str, new_root: &str) -> Result<RemapReport, String> {
+    let old_root = normalize_path(old_root).trim_end_matches('/').to_string();
+    let new_root = normalize_path(new_root).trim_end_matches('/').to_string();
+    if old_root == new_root {
+        return Ok(RemapReport::default());
+    }
+    if !Path::new(&new_root).is_dir() {
+        return Err(format!("{} is not a folder", new_root));
+    }
+
+    let folders = changes::get_folders(conn).map_err(|e| e.to_string())?;
+    if !folders.iter().any(|f| f.path.trim_end_matches('/') == old_root) {
+        return Err(format!("{} is not a library folder", old_root));
+    }
+    if folders.iter().any(|f| f.path.trim_end_matches('/') == new_root) {
+        return Err(format!("{} is already a library folder", new_root));
+    }
+
+    let mut report = RemapReport::default();
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    // Roots first: the path triggers resolve items against the folders table
+    let moved: Vec<(i64, String, String)> = folders
+        .iter()
+        .filter_map(|f| {
+            let old = f.path.trim_end_matches('/');
+            rebase(old, &old_root, &new_root).map(|p| (f.id, old.to_string(), p))
+        })
+        .collect();
+    for (id, _, path) in &moved {
+        tx.execute("UPDATE folders SET path = ?1 WHERE id = ?2", params![path, id])
+            .map_err(|e| e.to_string())?;
+    }
+    report.folders = moved.len();
+
+    for (id, old, root) in &moved {
+        report.items += tx
+            .execute(
+                "UPDATE media_items SET path = ?1 || '/' || rel_path WHERE folder_id = ?2 AND rel_path IS NOT NULL",
+                params![root, id],
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(f, _) if f.code == rusqlite::ErrorCode::ConstraintViolation => {
+                    format!("Some files under {} are already indexed separately; remove them first", root)
+                }
+                e => e.to_string(),
+            })?;
+        // Trashed files sit in the folder's own trash directory
+        tx.execute(
+            "UPDATE media_items SET trash_path = ?1 || substr(trash_path, length(?2) + 1)
+             WHERE folder_id = ?3 AND substr(trash_path, 1, length(?2) + 1) = ?2 || '/'",
+            params![root, old, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    for mut feed in changes::get_feeds(&tx).map_err(|e| e.to_string())? {
+        let folder_paths = rebase_json_paths(&feed.folder_paths, &old_root, &new_root);
+        // Older feeds keep a copy of the folders inside their filters too
+        let filter_config = serde_json::from_str::<serde_json::Value>(&feed.filter_config)
+            .ok()
+            .and_then(|mut config| {
+                let inner = config.get("folder_paths")?.to_string();
+                let rebased = rebase_json_paths(&inner, &old_root, &new_root)?;
+                config["folder_paths"] = serde_json::from_str(&rebased).ok()?;
+                Some(config.to_string())
+            });
+        if folder_paths.is_none() && filter_config.is_none() {
+            continue;
+        }
+        if let Some(paths) = folder_paths {
+            feed.folder_paths = paths;
+        }
+        if let Some(config) = filter_config {
+            feed.filter_config = config;
+        }
+        changes::save_feed(&tx, feed).map_err(|e| e.to_string())?;
+        report.feeds += 1;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(report)
+}
@@ -1,91 +1,201 @@
-use rusqlite::{params, Connection};
+use crate::db::{self, changes, FolderSettings};
+use crate::exclude::{self, Excluder};
+use crate::{formats, fsops, normalize_path, placeholder, trash};
+use rayon::prelude::*;
+use rusqlite::params;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
 
-pub fn scan_directory(folder_path: &str, db_path: &Path, recursive: bool) -> Result<usize, String> {
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+fn is_hidden(entry: &DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if entry.metadata().is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0) {
+            return true;
+        }
+    }
+    false
+}
+
+pub fn scan_directory(folder_path: &str, db_path: &Path, settings: &FolderSettings) -> Result<usize, String> {
+    let conn = db::open(db_path).map_err(|e| e.to_string())?;
 
     conn.execute("PRAGMA synchronous = OFF", []).ok();
     conn.execute("PRAGMA journal_mode = WAL", []).ok();
 
     let mut count = 0;
-    let supported_exts = [
-        "jpg", "jpeg", "png", "gif", "webp", "mp4", "webm", "mov", "mkv",
-    ];
-
-    let walker = WalkDir::new(folder_path);
-    let walker = if !recursive {
-        walker.max_depth(1)
-    } else {
-        walker
-    };
-
-    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+    let enabled = formats::enabled_ids(&conn);
+    let known = known_files(&conn, folder_path)?;
+
+    let mut walker = WalkDir::new(folder_path).follow_links(settings.follow_symlinks);
+    // Depth 1 is the folder's own files
+    let levels = if settings.recursive { settings.max_depth } else { Some(0) };
+    if let Some(levels) = levels {
+        walker = walker.max_depth(levels as usize + 1);
+    }
+    let mut excluder = Excluder::for_folder(&conn, folder_path, &settings.exclude_patterns);
+    let walker = walker.into_iter().filter_entry(|e| {
+        if e.depth() == 0 {
+            return true;
+        }
+        if e.file_name() == trash::TRASH_DIR {
+            return false;
+        }
+        if !settings.include_hidden && is_hidden(e) {
+            return false;
+        }
+        let rel_path = e.path().strip_prefix(folder_path).unwrap_or(e.path());
+        let rel_path = rel_path.to_string_lossy().replace('\\', "/");
+        !excluder.is_excluded(&rel_path, e.file_type().is_dir())
+    });
+
+    for entry in walker.filter_map(|e| e.ok()) {
         let path = entry.path();
         if path.is_file() {
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if supported_exts.contains(&ext_str.as_str()) {
-                    let metadata = fs::metadata(path).ok();
-                    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-                    let created = metadata
-                        .as_ref()
-                        .and_then(|m| m.created().ok())
+            if let Some(format) = formats::detect(path).filter(|f| enabled.contains(&f.id)) {
+                let metadata = fs::metadata(path).ok();
+                let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+                let unix_secs = |t: std::io::Result<std::time::SystemTime>| {
+                    t.ok()
                         .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| d.as_secs())
-                        .unwrap_or(0);
-
-                    let mut width = None;
-                    let mut height = None;
-                    let mut duration_sec = None;
-
-                    let is_video = ["mp4", "webm", "mov", "mkv"].contains(&ext_str.as_str());
-                    let file_type = if is_video {
-                        if ext_str == "mp4" || ext_str == "mov" {
-                            if let Ok(file) = fs::File::open(path) {
-                                let size = file.metadata().map(|m| m.len()).unwrap_or(0);
-                                if let Ok(reader) = mp4::Mp4Reader::read_header(file, size) {
-                                    duration_sec = Some(reader.duration().as_secs_f64());
-                                }
-                            }
-                        }
-                        "video"
-                    } else {
-                        if let Ok(dims) = image::image_dimensions(path) {
-                            width = Some(dims.0 as i32);
-                            height = Some(dims.1 as i32);
-                        }
-                        "image"
-                    };
+                        .map(|d| d.as_secs() as i64)
+                };
+                let created = metadata.as_ref().and_then(|m| unix_secs(m.created())).unwrap_or(0);
+                let modified = metadata.as_ref().and_then(|m| unix_secs(m.modified()));
 
+                let stored_path = normalize_path(&path.to_string_lossy());
+                let stored = known.get(&stored_path);
+                // Probing and hashing are the slow part of a rescan; unchanged files need neither
+                if stored.is_some_and(|&(s, m)| s == size as i64 && m.is_some() && m == modified) {
+                    count += 1;
+                    continue;
+                }
+
+                let probe = format.probe(path).unwrap_or_default();
+                let file_type = format.kind.as_str();
+
+                let quick_hash = fsops::quick_hash(path).ok();
+
+                if stored.is_some() {
+                    // Changed in place: refresh what was read from the file. A new size or
+                    // modification time means new pixels, so the placeholder is redone too.
                     conn.execute(
-                        "INSERT OR IGNORE INTO media_items (path, file_type, size_bytes, created_at, width, height, duration_sec) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        "UPDATE media_items SET
+                             blurhash = CASE WHEN size_bytes != ?2 OR modified_at != ?10 THEN NULL ELSE blurhash END,
+                             size_bytes = ?2, width = ?3, height = ?4, duration_sec = ?5, quick_hash = ?6,
+                             is_animated = ?7, frame_count = ?8, loop_count = ?9, modified_at = ?10
+                         WHERE path = ?1",
                         params![
-                            path.to_string_lossy(),
-                            file_type,
+                            stored_path,
                             size as i64,
-                            created as i64,
-                            width,
-                            height,
-                            duration_sec
+                            probe.width,
+                            probe.height,
+                            probe.duration_sec,
+                            quick_hash,
+                            probe.frame_count.is_some_and(|n| n > 1),
+                            probe.frame_count,
+                            probe.loop_count,
+                            modified
                         ],
                     ).ok();
-
                     count += 1;
+                    continue;
                 }
+
+                conn.execute(
+                    "INSERT OR IGNORE INTO media_items (path, file_type, size_bytes, created_at, width, height, duration_sec, quick_hash, is_animated, frame_count, loop_count, added_at, modified_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        stored_path,
+                        file_type,
+                        size as i64,
+                        created,
+                        probe.width,
+                        probe.height,
+                        probe.duration_sec,
+                        quick_hash,
+                        probe.frame_count.is_some_and(|n| n > 1),
+                        probe.frame_count,
+                        probe.loop_count,
+                        db::unix_now(),
+                        modified
+                    ],
+                ).ok();
+
+                count += 1;
             }
         }
     }
 
+    changes::mark_folder_scanned(&conn, folder_path).map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+/// Size and modification time of every row already stored below `folder_path`, by path.
+fn known_files(conn: &rusqlite::Connection, folder_path: &str) -> Result<HashMap<String, (i64, Option<i64>)>, String> {
+    let root = normalize_path(folder_path);
+    let root = root.trim_end_matches('/');
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, size_bytes, modified_at FROM media_items
+             WHERE substr(path, 1, length(?1) + 1) = ?1 || '/'",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![root], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(rows)
+}
+
+/// Rescans a library folder with its stored settings, then drops rows its exclude rules
+/// now cover (e.g. after a `.xcrollerignore` was edited).
+pub fn rescan_folder(db_path: &Path, folder: &db::Folder) -> Result<usize, String> {
+    let count = scan_directory(&folder.path, db_path, &folder.settings)?;
+    let conn = db::open(db_path).map_err(|e| e.to_string())?;
+    exclude::prune_folder(&conn, folder)?;
     Ok(count)
 }
 
+/// Rescans active folders whose auto-rescan interval has passed. Returns (path, files) per folder.
+pub fn rescan_due(db_path: &Path) -> Result<Vec<(String, usize)>, String> {
+    let conn = db::open(db_path).map_err(|e| e.to_string())?;
+    let now = db::unix_now();
+    let due: Vec<db::Folder> = changes::get_folders(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|f| f.settings.is_active)
+        .filter(|f| match (f.settings.rescan_interval_min, f.last_scanned_at) {
+            (Some(minutes), Some(last)) => now - last >= minutes * 60,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+        .collect();
+    drop(conn);
+
+    let mut counts = Vec::new();
+    for folder in due {
+        // Unplugged drives are skipped until they come back
+        if !Path::new(&folder.path).is_dir() {
+            continue;
+        }
+        let count = rescan_folder(db_path, &folder)?;
+        counts.push((folder.path, count));
+    }
+    Ok(counts)
+}
+
 pub fn backfill_metadata(db_path: &Path) -> Result<(), String> {
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let conn = db::open(db_path).map_err(|e| e.to_string())?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, path, file_type FROM media_items WHERE (width IS NULL AND file_type = 'image') OR (duration_sec IS NULL AND file_type = 'video')"
+        "SELECT id, path, quick_hash IS NULL FROM media_items WHERE (width IS NULL AND file_type = 'image') OR (duration_sec IS NULL AND file_type = 'video') OR (frame_count IS NULL AND file_type = 'image') OR quick_hash IS NULL"
     ).map_err(|e| e.to_string())?;
 
     let items = stmt
@@ -93,48 +203,40 @@ pub fn backfill_metadata(db_path: &Path) -> Result<(), String> {
             Ok((
                 row.get::<_, i64>(0)?,
                 row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
+                row.get::<_, bool>(2)?,
             ))
         })
         .map_err(|e| e.to_string())?;
 
     for item in items.filter_map(Result::ok) {
-        let (id, path_str, file_type) = item;
+        let (id, path_str, needs_hash) = item;
         let path = Path::new(&path_str);
         if !path.exists() {
             continue;
         }
 
-        let mut width = None;
-        let mut height = None;
-        let mut duration_sec = None;
-
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        if file_type == "video" {
-            if ext == "mp4" || ext == "mov" {
-                if let Ok(file) = fs::File::open(path) {
-                    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
-                    if let Ok(reader) = mp4::Mp4Reader::read_header(file, size) {
-                        duration_sec = Some(reader.duration().as_secs_f64());
-                    }
-                }
-            }
-        } else {
-            if let Ok(dims) = image::image_dimensions(path) {
-                width = Some(dims.0 as i32);
-                height = Some(dims.1 as i32);
+        if needs_hash {
+            if let Ok(hash) = fsops::quick_hash(path) {
+                conn.execute(
+                    "UPDATE media_items SET quick_hash = ?1 WHERE id = ?2",
+                    params![hash, id],
+                )
+                .ok();
             }
         }
 
-        if width.is_some() || duration_sec.is_some() {
+        let Some(format) = formats::detect(path) else {
+            continue;
+        };
+        let probe = format.probe(path).unwrap_or_default();
+
+        if probe.width.is_some() || probe.duration_sec.is_some() || probe.frame_count.is_some() {
+            // Keep what's already known (e.g. video sizes reported by the frontend)
             conn.execute(
-                "UPDATE media_items SET width = ?1, height = ?2, duration_sec = ?3 WHERE id = ?4",
-                params![width, height, duration_sec, id],
+                "UPDATE media_items SET width = COALESCE(?1, width), height = COALESCE(?2, height), duration_sec = COALESCE(?3, duration_sec),
+                 frame_count = COALESCE(?4, frame_count), loop_count = COALESCE(?5, loop_count), is_animated = COALESCE(?4, frame_count, 1) > 1
+                 WHERE id = ?6",
+                params![probe.width, probe.height, probe.duration_sec, probe.frame_count, probe.loop_count, id],
             )
             .ok();
         }
@@ -142,3 +244,47 @@ pub fn backfill_metadata(db_path: &Path) -> Result<(), String> {
 
     Ok(())
 }
+
+/// Computes loading placeholders for images that don't have one yet. Decoding is the slow
+/// part, so it runs in parallel; videos get theirs from a frame the frontend samples.
+pub fn backfill_placeholders(db_path: &Path) -> Result<usize, String> {
+    let conn = db::open(db_path).map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, path FROM media_items WHERE blurhash IS NULL AND file_type = 'image' AND trashed_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let items: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+
+    let mut count = 0;
+    // Chunked so results are saved as they come instead of all at the end
+    for chunk in items.chunks(64) {
+        let placeholders: Vec<(i64, Option<placeholder::Placeholder>)> = chunk
+            .par_iter()
+            .filter_map(|(id, path)| {
+                let path = Path::new(path);
+                // Offline files get another try on a later run
+                if !path.exists() {
+                    return None;
+                }
+                let placeholder = formats::detect(path).and_then(|format| placeholder::from_file(path, format).ok());
+                Some((*id, placeholder))
+            })
+            .collect();
+        for (id, p) in &placeholders {
+            match p {
+                Some(p) => {
+                    changes::update_media_placeholder(&conn, *id, p).map_err(|e| e.to_string())?;
+                    count += 1;
+                }
+                // Corrupt or undecodable files are tried once
+                None => changes::mark_placeholder_failed(&conn, *id).map_err(|e| e.to_string())?,
+            }
+        }
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,479 @@
+use crate::db::{self, changes, FilterOptions};
+use crate::formats;
+use crate::transform::{ImageTransform, OutputFormat};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+pub const CONFIG_SETTING: &str = "http_server";
+const DEFAULT_PORT: u16 = 8787;
+const DEFAULT_THUMB_SIZE: u32 = 320;
+const INDEX_HTML: &str = include_str!("web/index.html");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerConfig {
+    pub enabled: bool,
+    /// Interfaces to listen on; "127.0.0.1" keeps the server on this machine
+    pub bind_addresses: Vec<String>,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            enabled: false,
+            bind_addresses: vec!["127.0.0.1".to_string()],
+            port: DEFAULT_PORT,
+            token: generate_token(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerStatus {
+    pub running: bool,
+    pub urls: Vec<String>,
+    pub config: ServerConfig,
+}
+
+struct Running {
+    servers: Vec<Arc<Server>>,
+    threads: Vec<JoinHandle<()>>,
+    urls: Vec<String>,
+}
+
+/// Tauri-managed handle to the running server, if any.
+#[derive(Default)]
+pub struct ServerState(Mutex<Option<Running>>);
+
+/// Everything a request handler needs; requests open their own connection.
+#[derive(Clone)]
+struct Context {
+    db_path: PathBuf,
+    thumb_dir: PathBuf,
+    token: String,
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("no OS random source");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn load_config(conn: &Connection) -> ServerConfig {
+    changes::get_setting(conn, CONFIG_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_config(conn: &Connection, config: &ServerConfig) -> Result<(), String> {
+    let value = serde_json::to_string(config).map_err(|e| e.to_string())?;
+    changes::set_setting(conn, CONFIG_SETTING, &value).map_err(|e| e.to_string())
+}
+
+impl ServerState {
+    pub fn status(&self, config: ServerConfig) -> ServerStatus {
+        let running = self.0.lock().unwrap();
+        ServerStatus {
+            running: running.is_some(),
+            urls: running.as_ref().map(|r| r.urls.clone()).unwrap_or_default(),
+            config,
+        }
+    }
+
+    /// (Re)starts the server on every configured interface. Fails without binding anything
+    /// if one of the addresses can't be used.
+    pub fn start(&self, config: &ServerConfig, db_path: &Path, thumb_dir: &Path) -> Result<(), String> {
+        self.stop();
+        if config.bind_addresses.is_empty() {
+            return Err("Choose at least one interface to listen on".to_string());
+        }
+        if config.token.len() < 16 {
+            return Err("The access token must be at least 16 characters".to_string());
+        }
+
+        let mut servers = Vec::new();
+        let mut urls = Vec::new();
+        for address in &config.bind_addresses {
+            let ip: std::net::IpAddr = address
+                .trim()
+                .parse()
+                .map_err(|_| format!("\"{}\" is not an IP address", address))?;
+            let socket = std::net::SocketAddr::new(ip, config.port);
+            let server = Server::http(socket).map_err(|e| format!("Can't listen on {}: {}", socket, e))?;
+            servers.push(Arc::new(server));
+            urls.push(format!("http://{}/", socket));
+        }
+
+        let ctx = Context {
+            db_path: db_path.to_path_buf(),
+            thumb_dir: thumb_dir.to_path_buf(),
+            token: config.token.clone(),
+        };
+        let threads = servers
+            .iter()
+            .map(|server| {
+                let server = Arc::clone(server);
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        let ctx = ctx.clone();
+                        // One thread per request so a long video stream doesn't block browsing
+                        thread::spawn(move || handle(request, &ctx));
+                    }
+                })
+            })
+            .collect();
+
+        *self.0.lock().unwrap() = Some(Running { servers, threads, urls });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.0.lock().unwrap().take() {
+            for server in &running.servers {
+                server.unblock();
+            }
+            for thread in running.threads {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+enum Reply {
+    Json(String),
+    File(PathBuf, Option<String>),
+    Bytes(Vec<u8>, &'static str),
+    Error(u16, String),
+}
+
+fn handle(request: Request, ctx: &Context) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let query = parse_query(query);
+
+    if *request.method() != Method::Get && *request.method() != Method::Head {
+        return send(request, Reply::Error(405, "Only GET is supported".to_string()));
+    }
+    // The page itself holds no library data; it asks for the token and calls the API
+    if path == "/" || path == "/index.html" {
+        return send(request, Reply::Bytes(INDEX_HTML.as_bytes().to_vec(), "text/html; charset=utf-8"));
+    }
+    if !authorized(&request, &query, &ctx.token) {
+        return send(request, Reply::Error(401, "Missing or invalid token".to_string()));
+    }
+
+    let range = header(&request, "Range");
+    let reply = route(path, &query, range, ctx).unwrap_or_else(|e| Reply::Error(500, e));
+    send(request, reply)
+}
+
+fn route(path: &str, query: &[(String, String)], range: Option<String>, ctx: &Context) -> Result<Reply, String> {
+    let conn = db::open(&ctx.db_path).map_err(|e| e.to_string())?;
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match parts.as_slice() {
+        ["api", "media"] => {
+            let limit = query_param(query, "limit")
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(100)
+                .clamp(1, 1000);
+            let offset = query_param(query, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+            let filters = match (query_param(query, "feed"), query_param(query, "filters")) {
+                (Some(feed), _) => {
+                    let id: i64 = feed.parse().map_err(|_| "Invalid feed id".to_string())?;
+                    match changes::get_feed(&conn, id) {
+                        Ok(feed) => feed.filters().map_err(|e| e.to_string())?,
+                        Err(rusqlite::Error::QueryReturnedNoRows) => {
+                            return Ok(Reply::Error(404, format!("Feed {} not found", id)))
+                        }
+                        Err(e) => return Err(e.to_string()),
+                    }
+                }
+                (None, Some(json)) => match serde_json::from_str(json) {
+                    Ok(filters) => filters,
+                    Err(e) => return Ok(Reply::Error(400, format!("Invalid filters: {}", e))),
+                },
+                (None, None) => FilterOptions::default(),
+            };
+            if let Err(e) = filters.validate() {
+                return Ok(Reply::Error(400, format!("Invalid filters: {}", e)));
+            }
+            let items = changes::get_media(&conn, limit, offset, filters).map_err(|e| e.to_string())?;
+            json(&items)
+        }
+        ["api", "feeds"] => json(&changes::get_feeds(&conn).map_err(|e| e.to_string())?),
+        ["api", "folders"] => json(&changes::get_folders(&conn).map_err(|e| e.to_string())?),
+        ["api", "media", id, "file"] => {
+            let Some(file) = servable_file(&conn, id)? else {
+                return Ok(Reply::Error(404, "Not found".to_string()));
+            };
+            Ok(Reply::File(file, range))
+        }
+        ["api", "media", id, "thumbnail"] => {
+            let Some(file) = servable_file(&conn, id)? else {
+                return Ok(Reply::Error(404, "Not found".to_string()));
+            };
+            let size = query_param(query, "size")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_THUMB_SIZE)
+                .clamp(64, 1024);
+            thumbnail(&file, id, size, &ctx.thumb_dir)
+        }
+        _ => Ok(Reply::Error(404, "Not found".to_string())),
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Reply, String> {
+    serde_json::to_string(value).map(Reply::Json).map_err(|e| e.to_string())
+}
+
+/// The file behind `id`, but only if it's in the library, not trashed and really lives
+/// under a registered folder (symlinks are resolved before comparing).
+fn servable_file(conn: &Connection, id: &str) -> Result<Option<PathBuf>, String> {
+    let Ok(id) = id.parse::<i64>() else {
+        return Ok(None);
+    };
+    let path: String = match conn.query_row(
+        &format!(
+            "SELECT path FROM media_items WHERE id = ?1 AND trashed_at IS NULL AND {}",
+            db::IN_ACTIVE_FOLDER
+        ),
+        params![id],
+        |row| row.get(0),
+    ) {
+        Ok(path) => path,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let Ok(file) = fs::canonicalize(&path) else {
+        return Ok(None);
+    };
+    let inside_library = changes::get_folders(conn)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter_map(|f| fs::canonicalize(&f.path).ok())
+        .any(|root| file.starts_with(root));
+    Ok(inside_library.then_some(file))
+}
+
+fn thumbnail(file: &Path, id: &str, size: u32, thumb_dir: &Path) -> Result<Reply, String> {
+    let transform = ImageTransform {
+        max_long_edge: Some(size),
+        format: Some(OutputFormat::Jpeg),
+        quality: Some(80),
+        strip_metadata: true,
+    };
+    let is_still = formats::detect(file).is_some_and(|f| f.decodable && f.id != "gif");
+    // Videos have no frame extractor yet, HEIC/AVIF/JXL no decoder; GIFs are small enough to show as-is
+    if !is_still {
+        return Ok(Reply::Error(404, "No thumbnail for this item".to_string()));
+    }
+
+    // Keyed by modification time so edited files get a fresh thumbnail
+    let modified = fs::metadata(file)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let cached = thumb_dir.join(format!("{}_{}_{}.jpg", id, size, modified));
+    if let Ok(bytes) = fs::read(&cached) {
+        return Ok(Reply::Bytes(bytes, "image/jpeg"));
+    }
+
+    let bytes = transform.render(file).map_err(|e| e.to_string())?;
+    if fs::create_dir_all(thumb_dir).is_ok() {
+        let _ = fs::write(&cached, &bytes);
+    }
+    Ok(Reply::Bytes(bytes, "image/jpeg"))
+}
+
+fn send(request: Request, reply: Reply) {
+    let result = match reply {
+        Reply::Json(body) => request.respond(
+            Response::from_string(body).with_header(content_type("application/json")),
+        ),
+        Reply::Bytes(body, mime) => request.respond(
+            Response::from_data(body)
+                .with_header(content_type(mime))
+                .with_header(header_pair("Cache-Control", "private, max-age=86400")),
+        ),
+        Reply::Error(status, message) => request.respond(
+            Response::from_string(message)
+                .with_status_code(status)
+                .with_header(content_type("text/plain; charset=utf-8")),
+        ),
+        Reply::File(path, range) => return send_file(request, &path, range.as_deref()),
+    };
+    if let Err(e) = result {
+        // Usually the client navigated away mid-response
+        eprintln!("HTTP response failed: {}", e);
+    }
+}
+
+fn send_file(request: Request, path: &Path, range: Option<&str>) {
+    let Ok(mut file) = File::open(path) else {
+        return send(request, Reply::Error(404, "Not found".to_string()));
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mime = mime_type(path);
+
+    let (status, start, end) = match range.map(|r| parse_range(r, len)) {
+        None => (200, 0, len.saturating_sub(1)),
+        Some(Some((start, end))) => (206, start, end),
+        Some(None) => {
+            let response = Response::empty(416).with_header(header_pair("Content-Range", &format!("bytes */{}", len)));
+            let _ = request.respond(response);
+            return;
+        }
+    };
+
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+    if file.seek(SeekFrom::Start(start)).is_err() {
+        return send(request, Reply::Error(500, "Seek failed".to_string()));
+    }
+    let mut headers = vec![
+        content_type(mime),
+        header_pair("Accept-Ranges", "bytes"),
+        header_pair("Cache-Control", "private, max-age=3600"),
+    ];
+    if status == 206 {
+        headers.push(header_pair("Content-Range", &format!("bytes {}-{}/{}", start, end, len)));
+    }
+
+    let response = Response::new(
+        StatusCode(status),
+        headers,
+        file.take(body_len),
+        Some(body_len as usize),
+        None,
+    );
+    let _ = request.respond(response);
+}
+
+/// Parses a single-range "bytes=" header into inclusive offsets. Multipart ranges aren't supported.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || len == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            // "bytes=-500": the last 500 bytes
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len - 1),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len - 1)),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+fn authorized(request: &Request, query: &[(String, String)], token: &str) -> bool {
+    // Media elements can't send headers, so the query string is accepted too
+    let presented = header(request, "Authorization")
+        .and_then(|v| v.strip_prefix("Bearer ").map(|t| t.trim().to_string()))
+        .or_else(|| query_param(query, "token").map(str::to_string));
+    presented.is_some_and(|p| constant_time_eq(p.as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn header_pair(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn content_type(mime: &str) -> Header {
+    header_pair("Content-Type", mime)
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    formats::by_extension(path)
+        .map(|f| f.mime)
+        .unwrap_or("application/octet-stream")
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn query_param<'a>(query: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+pub fn thumbnail_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("thumbnails")
+}
+
+/// Starts the server at launch when it was left enabled.
+pub fn start_if_enabled(state: &ServerState, db_path: &Path, data_dir: &Path) {
+    let Ok(conn) = db::open(db_path) else {
+        return;
+    };
+    let config = load_config(&conn);
+    if config.enabled {
+        if let Err(e) = state.start(&config, db_path, &thumbnail_dir(data_dir)) {
+            eprintln!("Failed to start HTTP server: {}", e);
+        }
+    }
+    // Persist a generated token so it stays stable across launches
+    if changes::get_setting(&conn, CONFIG_SETTING).ok().flatten().is_none() {
+        let _ = save_config(&conn, &config);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::db;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Multipliers for `sort_by = "weighted"`; an item's weight is the product of those that
+/// apply to it, so 1 is neutral, above 1 a boost and below 1 a penalty.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ShuffleWeights {
+    pub unseen: f64,
+    pub starred: f64,
+    /// Added to the library within `recent_days`
+    pub recent: f64,
+    pub recent_days: i64,
+    /// Viewed within `viewed_days`
+    pub recently_viewed: f64,
+    pub viewed_days: i64,
+}
+
+impl Default for ShuffleWeights {
+    fn default() -> Self {
+        ShuffleWeights {
+            unseen: 3.0,
+            starred: 2.0,
+            recent: 1.5,
+            recent_days: 30,
+            recently_viewed: 0.2,
+            viewed_days: 7,
+        }
+    }
+}
+
+impl ShuffleWeights {
+    pub fn validate(&self) -> Result<(), String> {
+        let factors = [self.unseen, self.starred, self.recent, self.recently_viewed];
+        if factors.iter().any(|f| !f.is_finite() || *f < 0.0 || *f > 100.0) {
+            return Err("Shuffle weights must be between 0 and 100".to_string());
+        }
+        if self.recent_days < 0 || self.viewed_days < 0 {
+            return Err("Shuffle day ranges can't be negative".to_string());
+        }
+        if self.recent_days > db::MAX_DAYS || self.viewed_days > db::MAX_DAYS {
+            return Err(format!("Shuffle day ranges can be at most {} days", db::MAX_DAYS));
+        }
+        Ok(())
+    }
+
+    /// SQL for an item's weight in a `get_media` query, as it was at `as_of`. Views after
+    /// that don't count, so the same `as_of` always gives the same weights.
+    pub fn weight_sql(&self, as_of: i64) -> String {
+        let viewed_since = as_of.saturating_sub(self.viewed_days.saturating_mul(DAY_SECS));
+        format!(
+            "(CASE WHEN first_viewed_at IS NULL OR first_viewed_at > {as_of} THEN {unseen} ELSE 1 END \
+             * CASE WHEN starred = 1 THEN {starred} ELSE 1 END \
+             * CASE WHEN COALESCE(added_at, created_at) BETWEEN {added_since} AND {as_of} THEN {recent} ELSE 1 END \
+             * CASE WHEN last_viewed_at >= {viewed_since} AND (last_viewed_at <= {as_of} OR EXISTS ( \
+                   SELECT 1 FROM media_view_log l WHERE l.media_id = media_items.id \
+                   AND l.viewed_at BETWEEN {viewed_since} AND {as_of})) \
+               THEN {recently_viewed} ELSE 1 END)",
+            as_of = as_of,
+            unseen = self.unseen,
+            starred = self.starred,
+            added_since = as_of.saturating_sub(self.recent_days.saturating_mul(DAY_SECS)),
+            recent = self.recent,
+            viewed_since = viewed_since,
+            recently_viewed = self.recently_viewed,
+        )
+    }
+}
+
+/// Sort key for weighted sampling without replacement (Efraimidis–Spirakis): ordering by it,
+/// descending, draws items with probability proportional to `weight`. The same seed and id
+/// always give the same key, which keeps later pages in the order of the first.
+pub fn key(seed: i64, id: i64, weight: f64) -> f64 {
+    if weight.is_nan() || weight <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    // splitmix64 of seed and id, mapped to (0, 1]
+    let mut x = (seed as u64) ^ (id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    let u = ((x >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+    u.ln() / weight
+}
+
+/// Makes `shuffle_key(seed, id, weight)` available to queries on `conn`.
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "shuffle_key",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(key(ctx.get(0)?, ctx.get(1)?, ctx.get(2)?)),
+    )
+}
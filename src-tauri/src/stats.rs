@@ -0,0 +1,190 @@
+use crate::db::{changes, FilterOptions, LibrarySummary, MediaItem, IN_ACTIVE_FOLDER};
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+
+const LARGEST_FILES: i64 = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatBucket {
+    pub key: String,
+    pub count: i64,
+    pub bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MissingMetadata {
+    pub dimensions: i64,
+    pub video_duration: i64,
+}
+
+/// Everything counts the items the feeds show: not trashed and not in an inactive folder.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LibraryStats {
+    pub totals: LibrarySummary,
+    pub favorites_share: f64, // 0.0-1.0 of all items
+    pub favorites_bytes: i64,
+    pub by_folder: Vec<StatBucket>,
+    pub by_extension: Vec<StatBucket>,
+    pub by_type: Vec<StatBucket>,
+    pub by_resolution: Vec<StatBucket>,
+    pub by_duration: Vec<StatBucket>,
+    /// By the month the item was added to the library ("YYYY-MM"), oldest first. Items
+    /// indexed before that was recorded fall back to the file's creation date.
+    pub by_month: Vec<StatBucket>,
+    pub largest: Vec<MediaItem>,
+    pub missing_metadata: MissingMetadata,
+}
+
+// Nested folders already resolve to the innermost one through folder_id
+const FOLDER_EXPR: &str = "COALESCE(f.path, '(outside library folders)')";
+
+// Text after the last '.', unless that dot belongs to a directory name
+const EXTENSION_EXPR: &str = "CASE
+    WHEN instr(m.path, '.') = 0 THEN ''
+    ELSE lower(replace(m.path, rtrim(m.path, replace(m.path, '.', '')), ''))
+END";
+
+const TYPE_EXPR: &str = "CASE WHEN m.is_animated THEN 'animated' ELSE m.file_type END";
+
+const RESOLUTION_EXPR: &str = "CASE
+    WHEN m.width IS NULL OR m.height IS NULL THEN 'unknown'
+    WHEN max(m.width, m.height) < 1280 THEN 'SD'
+    WHEN max(m.width, m.height) < 1920 THEN '720p'
+    WHEN max(m.width, m.height) < 2560 THEN '1080p'
+    WHEN max(m.width, m.height) < 3840 THEN '1440p'
+    WHEN max(m.width, m.height) < 7680 THEN '4K'
+    ELSE '8K+'
+END";
+
+const DURATION_EXPR: &str = "CASE
+    WHEN m.duration_sec IS NULL THEN 'unknown'
+    WHEN m.duration_sec < 10 THEN '< 10s'
+    WHEN m.duration_sec < 30 THEN '10-30s'
+    WHEN m.duration_sec < 60 THEN '30s-1m'
+    WHEN m.duration_sec < 300 THEN '1-5m'
+    WHEN m.duration_sec < 1200 THEN '5-20m'
+    WHEN m.duration_sec < 3600 THEN '20-60m'
+    ELSE '> 1h'
+END";
+
+fn buckets(conn: &Connection, key_expr: &str, extra_where: &str, order: &str) -> Result<Vec<StatBucket>> {
+    let sql = format!(
+        "SELECT {key} AS bucket, COUNT(*), COALESCE(SUM(m.size_bytes), 0)
+         FROM media_items m LEFT JOIN folders f ON f.id = m.folder_id
+         WHERE m.trashed_at IS NULL AND {active} {extra}
+         GROUP BY bucket ORDER BY {order}",
+        key = key_expr,
+        active = IN_ACTIVE_FOLDER,
+        extra = extra_where,
+        order = order,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(StatBucket {
+                key: row.get(0)?,
+                count: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(rows)
+}
+
+/// Keeps histogram buckets in their natural order instead of alphabetical.
+fn in_order(mut buckets: Vec<StatBucket>, order: &[&str]) -> Vec<StatBucket> {
+    buckets.sort_by_key(|b| order.iter().position(|k| *k == b.key).unwrap_or(order.len()));
+    buckets
+}
+
+pub fn library_stats(conn: &Connection) -> Result<LibraryStats> {
+    let totals = changes::get_library_summary(conn)?;
+    let favorites_bytes: i64 = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(size_bytes), 0) FROM media_items WHERE starred = 1 AND trashed_at IS NULL AND {}",
+            IN_ACTIVE_FOLDER
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut by_extension = buckets(conn, EXTENSION_EXPR, "", "3 DESC")?;
+    for bucket in &mut by_extension {
+        if bucket.key.contains('/') {
+            bucket.key.clear();
+        }
+    }
+
+    let largest = changes::get_media(
+        conn,
+        LARGEST_FILES,
+        0,
+        FilterOptions {
+            sort_by: Some("size_bytes".to_string()),
+            sort_order: Some("desc".to_string()),
+            ..Default::default()
+        },
+    )?;
+
+    let missing_metadata = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(width IS NULL OR height IS NULL), 0),
+                    COALESCE(SUM(file_type = 'video' AND duration_sec IS NULL), 0)
+             FROM media_items WHERE trashed_at IS NULL AND {}",
+            IN_ACTIVE_FOLDER
+        ),
+        [],
+        |row| {
+            Ok(MissingMetadata {
+                dimensions: row.get(0)?,
+                video_duration: row.get(1)?,
+            })
+        },
+    )?;
+
+    Ok(LibraryStats {
+        favorites_share: if totals.items > 0 {
+            totals.starred as f64 / totals.items as f64
+        } else {
+            0.0
+        },
+        favorites_bytes,
+        by_folder: buckets(conn, FOLDER_EXPR, "", "3 DESC")?,
+        by_extension: merge_empty_keys(by_extension),
+        by_type: buckets(conn, TYPE_EXPR, "", "3 DESC")?,
+        by_resolution: in_order(
+            buckets(conn, RESOLUTION_EXPR, "", "1")?,
+            &["SD", "720p", "1080p", "1440p", "4K", "8K+", "unknown"],
+        ),
+        by_duration: in_order(
+            buckets(conn, DURATION_EXPR, "AND m.file_type = 'video'", "1")?,
+            &["< 10s", "10-30s", "30s-1m", "1-5m", "5-20m", "20-60m", "> 1h", "unknown"],
+        ),
+        by_month: buckets(
+            conn,
+            "COALESCE(strftime('%Y-%m', COALESCE(m.added_at, m.created_at), 'unixepoch'), 'unknown')",
+            "",
+            "1",
+        )?,
+        largest,
+        missing_metadata,
+        totals,
+    })
+}
+
+/// Files without an extension can land in several SQL groups; fold them into one.
+fn merge_empty_keys(buckets: Vec<StatBucket>) -> Vec<StatBucket> {
+    let mut merged: Vec<StatBucket> = Vec::with_capacity(buckets.len());
+    for bucket in buckets {
+        match merged.iter_mut().find(|b| b.key == bucket.key) {
+            Some(existing) => {
+                existing.count += bucket.count;
+                existing.bytes += bucket.bytes;
+            }
+            None => merged.push(bucket),
+        }
+    }
+    merged.sort_by_key(|b| std::cmp::Reverse(b.bytes));
+    merged
+}
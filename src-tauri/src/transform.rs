@@ -0,0 +1,191 @@
+use crate::db::MediaItem;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageEncoder, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+const DEFAULT_QUALITY: u8 = 85;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp, // Always lossless, so `quality` doesn't apply; image 0.24's lossy WebP encoder is deprecated
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+        }
+    }
+
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "png" => Some(OutputFormat::Png),
+            "webp" => Some(OutputFormat::Webp),
+            _ => None,
+        }
+    }
+}
+
+/// Re-encoding applied to still images on export. Originals are never modified.
+///
+/// The `image` crate can't write EXIF, so every re-encoded file comes out without
+/// metadata; `strip_metadata` forces a re-encode even when nothing else changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImageTransform {
+    pub max_long_edge: Option<u32>,
+    pub format: Option<OutputFormat>,
+    pub quality: Option<u8>, // 1-100, JPEG only
+    #[serde(default)]
+    pub strip_metadata: bool,
+}
+
+impl ImageTransform {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_long_edge == Some(0) {
+            return Err("max_long_edge must be greater than 0".to_string());
+        }
+        if let Some(q) = self.quality {
+            if !(1..=100).contains(&q) {
+                return Err("quality must be between 1 and 100".to_string());
+            }
+            if matches!(self.format, Some(OutputFormat::Png | OutputFormat::Webp)) {
+                return Err("quality only applies to JPEG output".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a file that comes out as `output` would be written unchanged.
+    fn is_noop(&self, output: OutputFormat) -> bool {
+        self.max_long_edge.is_none()
+            && self.format.is_none()
+            && !self.strip_metadata
+            && (self.quality.is_none() || output != OutputFormat::Jpeg)
+    }
+
+    /// Whether `item` gets re-encoded; videos and animations are exported untouched.
+    pub fn applies_to(&self, item: &MediaItem) -> bool {
+        if item.file_type != "image" || item.is_animated {
+            return false;
+        }
+        let ext = Path::new(&item.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if self.is_noop(self.output_format(&ext)) {
+            return false;
+        }
+        matches!(
+            ImageFormat::from_path(&item.path),
+            Ok(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Bmp | ImageFormat::Tiff)
+        )
+    }
+
+    fn output_format(&self, ext: &str) -> OutputFormat {
+        self.format
+            .or_else(|| OutputFormat::from_extension(ext))
+            .unwrap_or(OutputFormat::Png)
+    }
+
+    /// Name of the transformed copy, e.g. "IMG_0001_1600px.webp". Same settings, same name.
+    pub fn output_name(&self, file_name: &str) -> String {
+        let path = Path::new(file_name);
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let format = self.output_format(&ext.to_lowercase());
+        // Keep "JPEG"/"jpeg" spellings when the format doesn't change
+        let ext = if OutputFormat::from_extension(&ext.to_lowercase()) == Some(format) {
+            ext
+        } else {
+            format.extension().to_string()
+        };
+
+        match self.max_long_edge {
+            Some(edge) => format!("{}_{}px.{}", stem, edge, ext),
+            None => format!("{}.{}", stem, ext),
+        }
+    }
+
+    /// Decodes `src`, applies the transform and returns the encoded file.
+    pub fn render(&self, src: &Path) -> io::Result<Vec<u8>> {
+        let mut img = image::open(src).map_err(io::Error::other)?;
+        // Metadata is dropped below, so bake the EXIF rotation into the pixels first
+        img = apply_orientation(img, exif_orientation(src));
+
+        if let Some(edge) = self.max_long_edge {
+            if img.width().max(img.height()) > edge {
+                img = img.resize(edge, edge, image::imageops::FilterType::Lanczos3);
+            }
+        }
+
+        let ext = src
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let mut out = Vec::new();
+        match self.output_format(&ext) {
+            OutputFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let rgb = img.to_rgb8();
+                JpegEncoder::new_with_quality(&mut out, self.quality.unwrap_or(DEFAULT_QUALITY))
+                    .write_image(&rgb, rgb.width(), rgb.height(), image::ColorType::Rgb8)
+                    .map_err(io::Error::other)?;
+            }
+            OutputFormat::Png => {
+                let rgba = img.to_rgba8();
+                PngEncoder::new(&mut out)
+                    .write_image(&rgba, rgba.width(), rgba.height(), image::ColorType::Rgba8)
+                    .map_err(io::Error::other)?;
+            }
+            OutputFormat::Webp => {
+                let rgba = img.to_rgba8();
+                WebPEncoder::new_lossless(&mut out)
+                    .write_image(&rgba, rgba.width(), rgba.height(), image::ColorType::Rgba8)
+                    .map_err(io::Error::other)?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn exif_orientation(path: &Path) -> Option<u32> {
+    let file = fs::File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+fn apply_orientation(img: DynamicImage, orientation: Option<u32>) -> DynamicImage {
+    match orientation {
+        Some(2) => img.fliph(),
+        Some(3) => img.rotate180(),
+        Some(4) => img.flipv(),
+        Some(5) => img.rotate90().fliph(),
+        Some(6) => img.rotate90(),
+        Some(7) => img.rotate270().fliph(),
+        Some(8) => img.rotate270(),
+        _ => img,
+    }
+}
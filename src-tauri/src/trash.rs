@@ -0,0 +1,279 @@
+use crate::db::{self, changes};
+use crate::{fsops, normalize_path};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const RETENTION_SETTING: &str = "trash_retention_days";
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+pub const MAX_RETENTION_DAYS: i64 = 36500;
+/// Trash kept inside each library folder, so trashing is a rename on the file's own volume
+pub const TRASH_DIR: &str = ".xcroller-trash";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedItem {
+    pub id: i64,
+    pub original_path: String,
+    pub trash_path: String,
+    pub file_type: String,
+    pub size_bytes: i64,
+    pub trashed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashFailure {
+    pub id: i64,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TrashReport {
+    pub succeeded: Vec<i64>,
+    pub failed: Vec<TrashFailure>,
+}
+
+impl TrashReport {
+    fn fail(&mut self, id: i64, message: impl ToString) {
+        self.failed.push(TrashFailure {
+            id,
+            message: message.to_string(),
+        });
+    }
+}
+
+/// Every folder trash directory in use. Another library sharing a folder uses the same one.
+fn trash_dirs(conn: &Connection) -> Result<Vec<PathBuf>, String> {
+    Ok(changes::get_folders(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|folder| Path::new(&folder.path).join(TRASH_DIR))
+        .collect())
+}
+
+/// A name in `trash_dir` no file holds yet. The id prefix keeps same-named files from
+/// different folders apart; the counter covers other libraries trashing into the same folder.
+fn free_trash_name(trash_dir: &Path, id: i64, file_name: &str) -> PathBuf {
+    let mut dest = trash_dir.join(format!("{}_{}", id, file_name));
+    let mut n = 2;
+    while dest.exists() {
+        dest = trash_dir.join(format!("{}-{}_{}", id, n, file_name));
+        n += 1;
+    }
+    dest
+}
+
+/// Moves the files behind `ids` into the trash of their library folder and hides their rows
+/// from every feed. Files outside every folder go to `fallback_dir`.
+pub fn trash_items(conn: &Connection, fallback_dir: &Path, ids: &[i64]) -> Result<TrashReport, String> {
+    let mut report = TrashReport::default();
+
+    for &id in ids {
+        let (path, root): (String, Option<String>) = match conn.query_row(
+            "SELECT m.path, f.path FROM media_items m LEFT JOIN folders f ON f.id = m.folder_id
+             WHERE m.id = ?1 AND m.trashed_at IS NULL",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(paths) => paths,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                report.fail(id, "not in the library or already trashed");
+                continue;
+            }
+            Err(e) => {
+                report.fail(id, e);
+                continue;
+            }
+        };
+
+        let src = Path::new(&path);
+        let file_name = src
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let trash_dir = match &root {
+            Some(root) => Path::new(root).join(TRASH_DIR),
+            None => fallback_dir.to_path_buf(),
+        };
+        if let Err(e) = fs::create_dir_all(&trash_dir) {
+            report.fail(id, e);
+            continue;
+        }
+        let dest = free_trash_name(&trash_dir, id, &file_name);
+
+        if let Err(e) = fsops::move_file(src, &dest) {
+            report.fail(id, e);
+            continue;
+        }
+
+        if let Err(e) = conn.execute(
+            "UPDATE media_items SET trashed_at = ?1, trash_path = ?2 WHERE id = ?3",
+            params![db::unix_now(), normalize_path(&dest.to_string_lossy()), id],
+        ) {
+            // Put the file back so it doesn't vanish without a trash record
+            let _ = fsops::move_file(&dest, src);
+            report.fail(id, e);
+            continue;
+        }
+        report.succeeded.push(id);
+    }
+
+    Ok(report)
+}
+
+/// Moves trashed files back to their original location. Never overwrites a file that took their place.
+pub fn restore_items(conn: &Connection, ids: &[i64]) -> Result<TrashReport, String> {
+    let mut report = TrashReport::default();
+
+    for &id in ids {
+        let (path, trash_path): (String, String) = match conn.query_row(
+            "SELECT path, trash_path FROM media_items WHERE id = ?1 AND trashed_at IS NOT NULL",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(paths) => paths,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                report.fail(id, "not in the trash");
+                continue;
+            }
+            Err(e) => {
+                report.fail(id, e);
+                continue;
+            }
+        };
+
+        let dest = Path::new(&path);
+        if dest.exists() {
+            report.fail(id, format!("{} already exists", path));
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(e) = fsops::move_file(Path::new(&trash_path), dest) {
+            report.fail(id, e);
+            continue;
+        }
+
+        conn.execute(
+            "UPDATE media_items SET trashed_at = NULL, trash_path = NULL WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        report.succeeded.push(id);
+    }
+
+    Ok(report)
+}
+
+pub fn list_trash(conn: &Connection) -> Result<Vec<TrashedItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, path, trash_path, file_type, size_bytes, trashed_at FROM media_items
+             WHERE trashed_at IS NOT NULL ORDER BY trashed_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let items = stmt
+        .query_map([], |row| {
+            Ok(TrashedItem {
+                id: row.get(0)?,
+                original_path: row.get(1)?,
+                trash_path: row.get(2)?,
+                file_type: row.get(3)?,
+                size_bytes: row.get(4)?,
+                trashed_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(items)
+}
+
+/// Permanently deletes trashed items, or only those older than `older_than_days`, along with
+/// the files their rows point at. Files in the trash directories that no row of this library
+/// points at may belong to another library sharing the folder, and are left alone. Folder
+/// trash directories that end up empty are removed.
+pub fn empty_trash(conn: &Connection, older_than_days: Option<i64>) -> Result<usize, String> {
+    let cutoff = older_than_days
+        .map(|days| db::unix_now().saturating_sub(days.saturating_mul(24 * 60 * 60)))
+        .unwrap_or(i64::MAX);
+
+    let expired: Vec<TrashedItem> = list_trash(conn)?
+        .into_iter()
+        .filter(|item| item.trashed_at <= cutoff)
+        .collect();
+    let removed = delete_trashed(conn, &expired)?;
+
+    for trash_dir in trash_dirs(conn)? {
+        // Only succeeds once it's empty
+        let _ = fs::remove_dir(&trash_dir);
+    }
+
+    Ok(removed)
+}
+
+/// Permanently deletes the trashed items of the library folder at `path`, which is about to
+/// be removed. Their rows would otherwise go with the folder and leave the files behind.
+pub fn discard_folder(conn: &Connection, path: &str) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.path, m.trash_path, m.file_type, m.size_bytes, m.trashed_at
+             FROM media_items m JOIN folders f ON f.id = m.folder_id
+             WHERE f.path = ?1 AND m.trashed_at IS NOT NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let items: Vec<TrashedItem> = stmt
+        .query_map(params![path], |row| {
+            Ok(TrashedItem {
+                id: row.get(0)?,
+                original_path: row.get(1)?,
+                trash_path: row.get(2)?,
+                file_type: row.get(3)?,
+                size_bytes: row.get(4)?,
+                trashed_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+    let removed = delete_trashed(conn, &items)?;
+    let _ = fs::remove_dir(Path::new(path).join(TRASH_DIR));
+    Ok(removed)
+}
+
+/// Deletes each item's trash file, then its row. Rows whose file can't be deleted are kept.
+fn delete_trashed(conn: &Connection, items: &[TrashedItem]) -> Result<usize, String> {
+    let mut removed = 0;
+    for item in items {
+        let trash_path = Path::new(&item.trash_path);
+        if trash_path.exists() {
+            if let Err(e) = fs::remove_file(trash_path) {
+                eprintln!("Failed to delete {:?}: {}", trash_path, e);
+                continue;
+            }
+        }
+        conn.execute("DELETE FROM media_items WHERE id = ?1", params![item.id])
+            .map_err(|e| e.to_string())?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+pub fn retention_days(conn: &Connection) -> i64 {
+    changes::get_setting(conn, RETENTION_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// Empties everything past the configured retention period.
+pub fn purge_expired(conn: &Connection) -> Result<usize, String> {
+    let days = retention_days(conn);
+    // 0 keeps trashed items until the trash is emptied by hand
+    if days <= 0 {
+        return Ok(0);
+    }
+    empty_trash(conn, Some(days))
+}
@@ -1,4 +1,5 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { useAppStore } from "./lib/store";
 import { Toolbar } from "./components/Toolbar";
 import { MediaGrid } from "./components/MediaGrid";
@@ -15,6 +16,16 @@ function App() {
       useAppStore.getState().fetchMedia(true);
     };
     init();
+
+    // Background rescans only refresh the folder lists, so the grid doesn't jump while browsing
+    const unlisten = listen("folders-rescanned", () => {
+      const { loadFolders, loadFolderTree } = useAppStore.getState();
+      loadFolders();
+      loadFolderTree();
+    });
+    return () => {
+      unlisten.then(u => u());
+    };
   }, []);
 
   return (
@@ -1,20 +1,67 @@
 import { useAppStore } from '../lib/store';
 import { motion, AnimatePresence } from 'framer-motion';
-import { X, Filter, SortAsc, SortDesc, Image as ImageIcon, Video, Layers, Calendar, HardDrive, Ruler, Clock, FileText } from 'lucide-react';
-import { FilterOptions } from '../lib/types';
+import { X, Filter, SortAsc, SortDesc, Image as ImageIcon, Video, Layers, Calendar, HardDrive, Ruler, Clock, FileText, Repeat, Palette, Folder as FolderIcon, ChevronRight, Eye, History, Play, Sparkles } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { FilterOptions, FolderNode, ShuffleWeights } from '../lib/types';
+
+// Mirrors ShuffleWeights::default() in the backend
+const DEFAULT_SHUFFLE_WEIGHTS: ShuffleWeights = {
+    unseen: 3,
+    starred: 2,
+    recent: 1.5,
+    recent_days: 30,
+    recently_viewed: 0.2,
+    viewed_days: 7,
+};
 
 interface FilterSidebarProps {
     isOpen: boolean;
     onClose: () => void;
 }
 
+function FolderTreeItem({ node, depth }: { node: FolderNode; depth: number }) {
+    const { browsePath, setBrowsePath } = useAppStore();
+    const [expanded, setExpanded] = useState(depth === 0);
+    const selected = browsePath === node.path;
+
+    return (
+        <div>
+            <div
+                className={`flex items-center gap-1 pr-2 py-1.5 rounded-lg transition-colors cursor-pointer ${selected ? 'bg-xcroller-red text-white' : 'hover:bg-white/5 text-white/70'} ${node.is_active ? '' : 'opacity-40'}`}
+                style={{ paddingLeft: depth * 12 + 4 }}
+                onClick={() => setBrowsePath(selected ? null : node.path)}
+                title={node.path}
+            >
+                <button
+                    onClick={(e) => { e.stopPropagation(); setExpanded(!expanded); }}
+                    className={`p-0.5 rounded transition-transform ${node.children.length ? '' : 'invisible'} ${expanded ? 'rotate-90' : ''}`}
+                >
+                    <ChevronRight size={12} />
+                </button>
+                <FolderIcon size={14} className="shrink-0" />
+                <span className="text-sm truncate flex-1">{node.name || node.path}</span>
+                <span className="text-[10px] font-mono opacity-60">{node.total_count}</span>
+            </div>
+            {expanded && node.children.map(child => (
+                <FolderTreeItem key={child.path} node={child} depth={depth + 1} />
+            ))}
+        </div>
+    );
+}
+
 export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
-    const { filters, setFilters } = useAppStore();
+    const { filters, setFilters, folderTree, loadFolderTree, browsePath, setBrowsePath } = useAppStore();
+
+    useEffect(() => {
+        if (isOpen) loadFolderTree();
+    }, [isOpen]);
 
     const updateFilter = (newFilters: Partial<FilterOptions>) => {
         setFilters(newFilters);
     };
 
+    const shuffleWeights = { ...DEFAULT_SHUFFLE_WEIGHTS, ...filters.shuffle_weights };
+
     return (
         <AnimatePresence>
             {isOpen && (
@@ -47,6 +94,25 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                         </div>
 
                         <div className="flex-1 overflow-y-auto p-6 space-y-8">
+                            {/* Folder Tree Section */}
+                            {folderTree.length > 0 && (
+                                <section className="space-y-4">
+                                    <h3 className="text-xs font-semibold text-xcroller-muted uppercase tracking-wider flex items-center justify-between gap-2">
+                                        <span className="flex items-center gap-2"><FolderIcon size={14} /> Folders</span>
+                                        {browsePath && (
+                                            <button onClick={() => setBrowsePath(null)} className="normal-case tracking-normal hover:text-xcroller-red transition-colors">
+                                                Show all
+                                            </button>
+                                        )}
+                                    </h3>
+                                    <div className="space-y-0.5">
+                                        {folderTree.map(node => (
+                                            <FolderTreeItem key={node.folder_id} node={node} depth={0} />
+                                        ))}
+                                    </div>
+                                </section>
+                            )}
+
                             {/* Sort Section */}
                             <section className="space-y-4">
                                 <h3 className="text-xs font-semibold text-xcroller-muted uppercase tracking-wider flex items-center gap-2">
@@ -59,7 +125,12 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                                         { id: 'size_bytes', label: 'File Size', icon: HardDrive },
                                         { id: 'resolution', label: 'Resolution', icon: Ruler },
                                         { id: 'duration_sec', label: 'Duration', icon: Clock },
+                                        { id: 'hue', label: 'Hue', icon: Palette },
+                                        { id: 'view_count', label: 'Most Viewed', icon: Eye },
+                                        { id: 'last_viewed', label: 'Last Viewed', icon: History },
+                                        { id: 'last_played', label: 'Last Played', icon: Play },
                                         { id: 'random', label: 'Random Shuffle', icon: Layers },
+                                        { id: 'weighted', label: 'Discovery Shuffle', icon: Sparkles },
                                     ].map((option) => (
                                         <button
                                             key={option.id}
@@ -76,6 +147,33 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                                         </button>
                                     ))}
                                 </div>
+                                {filters.sort_by === 'weighted' && (
+                                    <div className="space-y-3 p-3 bg-white/5 rounded-lg border border-white/5">
+                                        <span className="text-[10px] text-xcroller-muted">How much more likely to come up (1× is neutral)</span>
+                                        {([
+                                            { key: 'unseen', label: 'Unseen' },
+                                            { key: 'starred', label: 'Starred' },
+                                            { key: 'recent', label: `Added in ${shuffleWeights.recent_days} days` },
+                                            { key: 'recently_viewed', label: `Viewed in ${shuffleWeights.viewed_days} days` },
+                                        ] as const).map(w => (
+                                            <div key={w.key} className="space-y-1">
+                                                <div className="flex justify-between text-[10px] text-xcroller-muted">
+                                                    <span>{w.label}</span>
+                                                    <span className="font-mono">{shuffleWeights[w.key]}×</span>
+                                                </div>
+                                                <input
+                                                    type="range"
+                                                    min="0"
+                                                    max="5"
+                                                    step="0.1"
+                                                    value={shuffleWeights[w.key]}
+                                                    onChange={(e) => updateFilter({ shuffle_weights: { ...shuffleWeights, [w.key]: parseFloat(e.target.value) } })}
+                                                    className="w-full h-1 bg-white/10 rounded-lg appearance-none cursor-pointer accent-xcroller-red"
+                                                />
+                                            </div>
+                                        ))}
+                                    </div>
+                                )}
                                 <div className="flex gap-2">
                                     <button
                                         onClick={() => updateFilter({ sort_order: 'asc' })}
@@ -104,6 +202,7 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                                         { id: 'all', label: 'All', icon: Layers },
                                         { id: 'image', label: 'Images', icon: ImageIcon },
                                         { id: 'video', label: 'Videos', icon: Video },
+                                        { id: 'animated', label: 'Animated', icon: Repeat },
                                     ].map((type) => (
                                         <button
                                             key={type.id}
@@ -142,7 +241,93 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                                 </div>
                             </section>
 
-                            {/* Duration Constraints (Videos) */}
+                            {/* Color Section */}
+                            <section className="space-y-4">
+                                <h3 className="text-xs font-semibold text-xcroller-muted uppercase tracking-wider flex items-center gap-2">
+                                    <Palette size={14} /> Color
+                                </h3>
+                                <div className="flex bg-black/40 p-1 rounded-xl">
+                                    {[
+                                        { id: undefined, label: 'Any' },
+                                        { id: false, label: 'Color' },
+                                        { id: true, label: 'Grayscale' },
+                                    ].map((o) => (
+                                        <button
+                                            key={o.label}
+                                            onClick={() => updateFilter({ monochrome: o.id })}
+                                            className={`flex-1 py-2 rounded-lg text-[10px] uppercase font-bold tracking-tight transition-all ${filters.monochrome === o.id ? 'bg-xcroller-red text-white shadow-lg' : 'text-white/50 hover:text-white'}`}
+                                        >
+                                            {o.label}
+                                        </button>
+                                    ))}
+                                </div>
+                                <div className="flex items-center gap-3">
+                                    <input
+                                        type="color"
+                                        value={filters.color || '#808080'}
+                                        onChange={(e) => updateFilter({ color: e.target.value })}
+                                        className="w-10 h-10 rounded-lg bg-transparent border border-white/10 cursor-pointer"
+                                    />
+                                    {filters.color ? (
+                                        <div className="flex-1 space-y-1">
+                                            <div className="flex justify-between text-[10px] text-xcroller-muted">
+                                                <span>Tolerance</span>
+                                                <button onClick={() => updateFilter({ color: undefined, color_tolerance: undefined })} className="hover:text-xcroller-red">Clear</button>
+                                            </div>
+                                            <input
+                                                type="range"
+                                                min="5"
+                                                max="60"
+                                                step="1"
+                                                value={filters.color_tolerance ?? 20}
+                                                onChange={(e) => updateFilter({ color_tolerance: parseFloat(e.target.value) })}
+                                                className="w-full h-1 bg-white/10 rounded-lg appearance-none cursor-pointer accent-xcroller-red"
+                                            />
+                                        </div>
+                                    ) : (
+                                        <span className="text-[10px] text-xcroller-muted">Pick a color to find items dominated by it</span>
+                                    )}
+                                </div>
+                            </section>
+
+                            {/* View History Section */}
+                            <section className="space-y-4">
+                                <h3 className="text-xs font-semibold text-xcroller-muted uppercase tracking-wider flex items-center gap-2">
+                                    <Eye size={14} /> Viewed
+                                </h3>
+                                <div className="flex bg-black/40 p-1 rounded-xl">
+                                    {[
+                                        { id: undefined, label: 'Any' },
+                                        { id: true, label: 'Unseen' },
+                                        { id: false, label: 'Seen' },
+                                    ].map((o) => (
+                                        <button
+                                            key={o.label}
+                                            onClick={() => updateFilter({ unseen: o.id })}
+                                            className={`flex-1 py-2 rounded-lg text-[10px] uppercase font-bold tracking-tight transition-all ${filters.unseen === o.id ? 'bg-xcroller-red text-white shadow-lg' : 'text-white/50 hover:text-white'}`}
+                                        >
+                                            {o.label}
+                                        </button>
+                                    ))}
+                                </div>
+                                <div className="flex items-center justify-between gap-3">
+                                    <span className="text-[10px] text-xcroller-muted">Not viewed in</span>
+                                    <select
+                                        value={filters.not_viewed_days ?? ''}
+                                        onChange={(e) => updateFilter({ not_viewed_days: e.target.value === '' ? undefined : Number(e.target.value) })}
+                                        className="bg-white/5 border border-white/10 rounded-lg px-2 py-1.5 text-xs focus:border-xcroller-red outline-none"
+                                    >
+                                        <option value="">Any time</option>
+                                        <option value="1">A day</option>
+                                        <option value="7">A week</option>
+                                        <option value="30">A month</option>
+                                        <option value="90">3 months</option>
+                                        <option value="365">A year</option>
+                                    </select>
+                                </div>
+                            </section>
+
+                            {/* Duration Constraints (Videos and animations) */}
                             {filters.media_type !== 'image' && (
                                 <section className="space-y-4">
                                     <h3 className="text-xs font-semibold text-xcroller-muted uppercase tracking-wider flex items-center gap-2">
@@ -186,6 +371,12 @@ export function FilterSidebar({ isOpen, onClose }: FilterSidebarProps) {
                                     min_height: undefined,
                                     min_size: undefined,
                                     max_size: undefined,
+                                    color: undefined,
+                                    color_tolerance: undefined,
+                                    monochrome: undefined,
+                                    unseen: undefined,
+                                    not_viewed_days: undefined,
+                                    shuffle_weights: undefined,
                                 })}
                                 className="w-full py-4 text-xs font-bold text-xcroller-muted hover:text-xcroller-red transition-colors border-t border-white/5"
                             >
@@ -1,11 +1,16 @@
 import { useEffect, useState, useRef, useMemo } from 'react';
-import { X, ChevronLeft, ChevronRight, Play, Pause, Volume2, VolumeX, Star } from 'lucide-react';
+import { X, ChevronLeft, ChevronRight, Play, Pause, Volume2, VolumeX, Star, Trash2 } from 'lucide-react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { useAppStore } from '../lib/store';
 import { convertFileSrc } from '@tauri-apps/api/core';
+import { trackView, ViewTracker } from '../lib/history';
+
+// Matches history::MIN_RESUME_DURATION_SEC; shorter videos aren't resumed
+const RESUMABLE_DURATION_SEC = 60;
+const SAVE_POSITION_EVERY_SEC = 10;
 
 export function FullscreenViewer() {
-    const { mediaItems, selectedMediaId, setSelectedMediaId, toggleStar } = useAppStore();
+    const { mediaItems, selectedMediaId, setSelectedMediaId, toggleStar, deleteMedia, savePlaybackPosition } = useAppStore();
     const [isPlaying, setIsPlaying] = useState(true);
     const [volume, setVolume] = useState(1);
     const [isMuted, setIsMuted] = useState(false);
@@ -14,12 +19,31 @@ export function FullscreenViewer() {
     const [showControls, setShowControls] = useState(true);
     const videoRef = useRef<HTMLVideoElement>(null);
     const controlsTimeoutRef = useRef<number | undefined>(undefined);
+    const viewRef = useRef<ViewTracker | null>(null);
+    // Playback of the current long video, saved periodically and when leaving it
+    const playbackRef = useRef<{ position: number; duration: number; savedAt: number } | null>(null);
 
     const selectedIndex = mediaItems.findIndex(i => i.id === selectedMediaId);
     const item = mediaItems[selectedIndex];
 
     const handleClose = () => setSelectedMediaId(null);
 
+    // One view per item shown, recorded when moving on or closing
+    useEffect(() => {
+        if (!item?.id) return;
+        const id = item.id;
+        const view = trackView(id);
+        viewRef.current = view;
+        playbackRef.current = null;
+        return () => {
+            view.end();
+            viewRef.current = null;
+            const playback = playbackRef.current;
+            if (playback) savePlaybackPosition(id, playback.position, playback.duration);
+            playbackRef.current = null;
+        };
+    }, [item?.id]);
+
     const handleNext = (e?: React.MouseEvent) => {
         e?.stopPropagation();
         if (selectedIndex < mediaItems.length - 1) {
@@ -27,6 +51,16 @@ export function FullscreenViewer() {
         }
     };
 
+    const handleDelete = async () => {
+        if (!item.id) return;
+        // Stay in the viewer on the neighbouring item
+        const neighbour = mediaItems[selectedIndex + 1] ?? mediaItems[selectedIndex - 1];
+        const deleted = await deleteMedia([item.id]);
+        if (deleted.includes(item.id)) {
+            setSelectedMediaId(neighbour?.id ?? null);
+        }
+    };
+
     const handlePrev = (e?: React.MouseEvent) => {
         e?.stopPropagation();
         if (selectedIndex > 0) {
@@ -89,6 +123,7 @@ export function FullscreenViewer() {
             if (e.key === 'f') {
                 if (item.id) toggleStar(item.id);
             }
+            if (e.key === 'Delete') handleDelete();
         };
         window.addEventListener('keydown', handleKeyDown);
         return () => window.removeEventListener('keydown', handleKeyDown);
@@ -99,11 +134,27 @@ export function FullscreenViewer() {
         const video = videoRef.current;
         if (!video) return;
 
-        const handleTimeUpdate = () => setCurrentTime(video.currentTime);
+        const handleTimeUpdate = () => {
+            setCurrentTime(video.currentTime);
+            viewRef.current?.onTimeUpdate(video.currentTime);
+            if (video.duration >= RESUMABLE_DURATION_SEC && item.id) {
+                const playback = playbackRef.current ?? { position: 0, duration: video.duration, savedAt: video.currentTime };
+                playback.position = video.currentTime;
+                playbackRef.current = playback;
+                if (Math.abs(video.currentTime - playback.savedAt) >= SAVE_POSITION_EVERY_SEC) {
+                    playback.savedAt = video.currentTime;
+                    savePlaybackPosition(item.id, video.currentTime, video.duration);
+                }
+            }
+        };
         const handleLoadedMetadata = () => {
             setDuration(video.duration);
             video.volume = volume;
             video.muted = isMuted;
+            // Pick up where this video was left off
+            if (item.playback_position && !playbackRef.current) {
+                video.currentTime = item.playback_position;
+            }
         };
         const handlePlay = () => setIsPlaying(true);
         const handlePause = () => setIsPlaying(false);
@@ -311,6 +362,18 @@ export function FullscreenViewer() {
                     <Star size={24} className={item.starred ? 'fill-current' : ''} />
                 </button>
 
+                {/* Delete Button */}
+                <button
+                    onClick={(e) => {
+                        e.stopPropagation();
+                        handleDelete();
+                    }}
+                    className="absolute top-4 right-28 p-2 bg-white/10 hover:bg-red-500/40 rounded-full text-white transition-colors z-50 pointer-events-auto"
+                    title="Move to Trash (Del)"
+                >
+                    <Trash2 size={24} />
+                </button>
+
                 {/* Close Button */}
                 <button
                     onClick={handleClose}
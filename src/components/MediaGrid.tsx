@@ -4,6 +4,7 @@ import { MediaItem } from '../lib/types';
 import { MediaTile } from './MediaTile';
 import { motion, AnimatePresence } from 'framer-motion';
 import { convertFileSrc } from '@tauri-apps/api/core';
+import { trackView, ViewTracker } from '../lib/history';
 
 export const MediaGrid = () => {
     const {
@@ -28,6 +29,18 @@ export const MediaGrid = () => {
     const gap = 6;     // Decreased gap
     const { hoverVolume } = useAppStore(); // Get volume
     const previewVideoRef = useRef<HTMLVideoElement>(null);
+    const previewViewRef = useRef<ViewTracker | null>(null);
+
+    // A hover preview held long enough counts as a view
+    useEffect(() => {
+        if (!hoveredItem?.id) return;
+        const view = trackView(hoveredItem.id);
+        previewViewRef.current = view;
+        return () => {
+            view.end();
+            previewViewRef.current = null;
+        };
+    }, [hoveredItem?.id]);
 
     // We need to track container width to calculate column width
     const [containerWidth, setContainerWidth] = useState(window.innerWidth - (padding * 2));
@@ -249,6 +262,7 @@ export const MediaGrid = () => {
                                     muted={false}
                                     loop
                                     playsInline
+                                    onTimeUpdate={(e) => previewViewRef.current?.onTimeUpdate(e.currentTarget.currentTime)}
                                 />
                             ) : (
                                 <img
@@ -1,8 +1,9 @@
 import React, { useState, useMemo } from 'react';
 import { MediaItem } from '../lib/types';
 import { cn } from '../lib/utils';
+import { blurhashToDataUrl, sampleVideoFrame } from '../lib/blurhash';
 import { motion } from 'framer-motion';
-import { Star, Play } from 'lucide-react';
+import { Star, Play, Repeat } from 'lucide-react';
 import { useAppStore } from '../lib/store';
 import { convertFileSrc } from '@tauri-apps/api/core';
 
@@ -32,6 +33,8 @@ export const MediaTile = React.memo(({ item, style, onHoverStart, onHoverEnd, on
         return colors[hash % colors.length];
     }, [item.path]);
 
+    const blurUrl = useMemo(() => item.blurhash ? blurhashToDataUrl(item.blurhash) : null, [item.blurhash]);
+
     // Use Tauri's optimized asset protocol for direct file streaming
     const assetUrl = useMemo(() => {
         // Append a small key to force re-evaluation on retry
@@ -71,8 +74,9 @@ export const MediaTile = React.memo(({ item, style, onHoverStart, onHoverEnd, on
                 className={cn(
                     "w-full h-full relative group rounded-xl overflow-hidden bg-xcroller-surface transition-all duration-500 origin-center border border-white/5",
                     isHovered ? "z-50 ring-2 ring-xcroller-red shadow-2xl" : "z-0 shadow-lg",
-                    placeholderColor
+                    !item.dominant_color && placeholderColor
                 )}
+                style={item.dominant_color ? { backgroundColor: item.dominant_color } : undefined}
                 onMouseEnter={handleMouseEnter}
                 onMouseLeave={handleMouseLeave}
                 onClick={() => {
@@ -91,6 +95,8 @@ export const MediaTile = React.memo(({ item, style, onHoverStart, onHoverEnd, on
                             <div className="text-xcroller-muted flex flex-col items-center gap-1">
                                 <span className="text-[10px] font-bold uppercase opacity-50">Error Loading</span>
                             </div>
+                        ) : blurUrl ? (
+                            <img src={blurUrl} alt="" className="absolute inset-0 w-full h-full object-cover" />
                         ) : (
                             <div className="animate-pulse w-8 h-8 rounded-full border-2 border-white/5 border-t-xcroller-red/40 animate-spin" />
                         )}
@@ -107,7 +113,17 @@ export const MediaTile = React.memo(({ item, style, onHoverStart, onHoverEnd, on
                             loop
                             playsInline
                             preload="metadata"
-                            onLoadedData={() => setIsLoaded(true)}
+                            onLoadedData={(e) => {
+                                setIsLoaded(true);
+                                if (!item.blurhash) {
+                                    const frame = sampleVideoFrame(e.currentTarget);
+                                    if (frame) {
+                                        import('@tauri-apps/api/core').then(m => {
+                                            m.invoke('update_media_poster', { id: item.id, ...frame }).catch(() => { });
+                                        });
+                                    }
+                                }
+                            }}
                             onError={handleLoadError}
                             onLoadedMetadata={async (e) => {
                                 const video = e.currentTarget;
@@ -169,6 +185,23 @@ export const MediaTile = React.memo(({ item, style, onHoverStart, onHoverEnd, on
                     </div>
                 )}
 
+                {/* Resume Progress */}
+                {item.playback_position && item.duration_sec ? (
+                    <div className="absolute bottom-0 left-0 right-0 h-1 bg-black/40 z-20" title="Continue watching">
+                        <div
+                            className="h-full bg-xcroller-red"
+                            style={{ width: `${Math.min(100, (item.playback_position / item.duration_sec) * 100)}%` }}
+                        />
+                    </div>
+                ) : null}
+
+                {/* Animation Indicator */}
+                {item.is_animated && (
+                    <div className="absolute top-2 right-2 bg-black/50 p-1.5 rounded-full backdrop-blur-sm" title={`${item.frame_count} frames`}>
+                        <Repeat size={12} className="text-white" />
+                    </div>
+                )}
+
                 {/* Actions (Star) */}
                 <button
                     onClick={(e) => {
@@ -1,8 +1,10 @@
-import { X, Trash2, Folder as FolderIcon, Plus, Layout, Check, Settings } from 'lucide-react';
+import { X, Trash2, Folder as FolderIcon, Plus, Layout, Check, Settings, Wifi, RefreshCw, Download, Upload, Database, RotateCcw, HeartPulse, FolderSync, SlidersHorizontal, Library as LibraryIcon, Pencil } from 'lucide-react';
 import { useAppStore } from '../lib/store';
 import { motion, AnimatePresence } from 'framer-motion';
-import { useState } from 'react';
-import { Feed } from '../lib/types';
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { open, save, message, ask } from '@tauri-apps/plugin-dialog';
+import { BackupInfo, BackupSchedule, Feed, FeedImportPreview, Folder, FolderSettings, FormatInfo, HealthIssue, HistoryStatus, Library, OrphanReport, ServerStatus } from '../lib/types';
 
 interface SettingsModalProps {
     isOpen: boolean;
@@ -13,12 +15,22 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     const {
         folderPaths,
         removeFolder,
+        remapFolder,
+        updateFolderSettings,
         autoScrollSpeed,
         setAutoScrollSpeed,
         feeds,
         saveFeed,
         deleteFeed,
-        filters
+        exportFeeds,
+        inspectFeedImport,
+        importFeeds,
+        checkLibraryHealth,
+        relinkMedia,
+        filters,
+        libraries,
+        loadLibraries,
+        switchLibrary
     } = useAppStore();
 
     const [isCreatingFeed, setIsCreatingFeed] = useState(false);
@@ -26,6 +38,389 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     const [newFeedName, setNewFeedName] = useState('');
     const [selectedFolders, setSelectedFolders] = useState<string[]>([]);
 
+    const [pendingImport, setPendingImport] = useState<{ path: string; preview: FeedImportPreview } | null>(null);
+    const [folderMap, setFolderMap] = useState<Record<string, string>>({});
+
+    const handleExportFeeds = async () => {
+        const target = await save({
+            title: 'Export Feeds',
+            defaultPath: 'xcroller-feeds.json',
+            filters: [{ name: 'Xcroller Feeds', extensions: ['json'] }]
+        });
+        if (!target) return;
+        try {
+            const count = await exportFeeds(target);
+            await message(`Exported ${count} feeds to ${target}`, { title: 'Export Complete', kind: 'info' });
+        } catch (e) {
+            await message(String(e), { title: 'Export Failed', kind: 'error' });
+        }
+    };
+
+    const runImport = async (path: string, map: Record<string, string>) => {
+        try {
+            const count = await importFeeds(path, map);
+            setPendingImport(null);
+            await message(`Imported ${count} feeds`, { title: 'Import Complete', kind: 'info' });
+        } catch (e) {
+            await message(String(e), { title: 'Import Failed', kind: 'error' });
+        }
+    };
+
+    const handleImportFeeds = async () => {
+        const path = await open({
+            multiple: false,
+            title: 'Import Feeds',
+            filters: [{ name: 'Xcroller Feeds', extensions: ['json'] }]
+        });
+        if (!path || typeof path !== 'string') return;
+        try {
+            const preview = await inspectFeedImport(path);
+            const invalid = preview.feeds.filter(f => f.error);
+            if (invalid.length > 0) {
+                await message(invalid.map(f => `${f.name}: ${f.error}`).join('\n'), { title: 'Invalid Feeds', kind: 'error' });
+                return;
+            }
+            const missing = preview.folders.filter(f => !f.found);
+            if (missing.length === 0) {
+                await runImport(path, {});
+                return;
+            }
+            // Ask where folders from the other machine live here
+            setFolderMap(Object.fromEntries(missing.map(f => [f.source, f.suggestion ?? ''])));
+            setPendingImport({ path, preview });
+        } catch (e) {
+            await message(String(e), { title: 'Import Failed', kind: 'error' });
+        }
+    };
+
+    const [healthIssues, setHealthIssues] = useState<HealthIssue[] | null>(null);
+    const [healthBusy, setHealthBusy] = useState(false);
+    const [healthResult, setHealthResult] = useState<string | null>(null);
+
+    const handleHealthCheck = async () => {
+        setHealthBusy(true);
+        setHealthResult(null);
+        try {
+            setHealthIssues(await checkLibraryHealth(true));
+        } catch (e) {
+            setHealthResult(`Failed: ${e}`);
+        } finally {
+            setHealthBusy(false);
+        }
+    };
+
+    const handleRelink = async () => {
+        const relinkable = (healthIssues ?? []).filter(i => i.status === 'missing' || i.status === 'offline');
+        const folder = await open({ directory: true, multiple: false, title: 'Where did the files go?' });
+        if (!folder || typeof folder !== 'string') return;
+        setHealthBusy(true);
+        try {
+            const report = await relinkMedia(relinkable.map(i => i.id), folder);
+            const fixed = new Set(report.relinked.map(r => r.id));
+            setHealthIssues(prev => prev?.filter(i => !fixed.has(i.id)) ?? null);
+            setHealthResult(
+                `Relinked ${report.relinked.length}` +
+                (report.ambiguous.length ? `, ${report.ambiguous.length} ambiguous` : '') +
+                (report.not_found.length ? `, ${report.not_found.length} not found` : '')
+            );
+        } catch (e) {
+            setHealthResult(`Failed: ${e}`);
+        } finally {
+            setHealthBusy(false);
+        }
+    };
+
+    const [newLibraryName, setNewLibraryName] = useState<string | null>(null);
+    const [renamingLibrary, setRenamingLibrary] = useState<{ id: string; name: string } | null>(null);
+    const [libraryError, setLibraryError] = useState<string | null>(null);
+
+    useEffect(() => {
+        if (!isOpen) return;
+        loadLibraries();
+    }, [isOpen]);
+
+    const handleSwitchLibrary = async (library: Library) => {
+        setLibraryError(null);
+        try {
+            await switchLibrary(library.id);
+            onClose();
+        } catch (e) {
+            setLibraryError(String(e));
+        }
+    };
+
+    const handleCreateLibrary = async () => {
+        if (newLibraryName === null) return;
+        setLibraryError(null);
+        try {
+            const library = await invoke<Library>('create_library', { name: newLibraryName });
+            setNewLibraryName(null);
+            await handleSwitchLibrary(library);
+        } catch (e) {
+            setLibraryError(String(e));
+        }
+    };
+
+    const handleOpenLibrary = async () => {
+        const path = await open({
+            multiple: false,
+            title: 'Open Library Database',
+            filters: [{ name: 'Xcroller Library', extensions: ['db'] }]
+        });
+        if (!path || typeof path !== 'string') return;
+        setLibraryError(null);
+        try {
+            const library = await invoke<Library>('open_library_file', { path });
+            await handleSwitchLibrary(library);
+        } catch (e) {
+            setLibraryError(String(e));
+        }
+    };
+
+    const handleRenameLibrary = async () => {
+        if (!renamingLibrary) return;
+        setLibraryError(null);
+        try {
+            await invoke('rename_library', { id: renamingLibrary.id, name: renamingLibrary.name });
+            setRenamingLibrary(null);
+            await loadLibraries();
+        } catch (e) {
+            setLibraryError(String(e));
+        }
+    };
+
+    const handleDeleteLibrary = async (library: Library) => {
+        const confirmed = await ask(
+            library.external
+                ? `Remove "${library.name}" from the list? Its database at ${library.db_path} is kept.`
+                : `Delete "${library.name}" with its folders, feeds, stars and thumbnails? Your media files are not touched.`,
+            { title: 'Delete Library', kind: 'warning' }
+        );
+        if (!confirmed) return;
+        setLibraryError(null);
+        try {
+            await invoke('delete_library', { id: library.id });
+            await loadLibraries();
+        } catch (e) {
+            setLibraryError(String(e));
+        }
+    };
+
+    const [expandedFolderId, setExpandedFolderId] = useState<number | null>(null);
+    const [folderError, setFolderError] = useState<string | null>(null);
+
+    const parsePatterns = (text: string) => text.split('\n').map(l => l.trim()).filter(Boolean);
+
+    const changeFolderSettings = async (folder: Folder, change: Partial<FolderSettings>) => {
+        const settings: FolderSettings = {
+            is_active: folder.is_active,
+            recursive: folder.recursive,
+            max_depth: folder.max_depth,
+            include_hidden: folder.include_hidden,
+            follow_symlinks: folder.follow_symlinks,
+            rescan_interval_min: folder.rescan_interval_min,
+            exclude_patterns: folder.exclude_patterns,
+        };
+        setFolderError(null);
+        try {
+            const pruned = await updateFolderSettings(folder.path, { ...settings, ...change });
+            if (pruned > 0) setFolderError(`Removed ${pruned} excluded items`);
+        } catch (e) {
+            setFolderError(String(e));
+        }
+    };
+
+    const handleRelocateFolder = async (oldRoot: string) => {
+        const newRoot = await open({ directory: true, multiple: false, title: `Where is ${oldRoot} now?` });
+        if (!newRoot || typeof newRoot !== 'string') return;
+        try {
+            const report = await remapFolder(oldRoot, newRoot);
+            await message(
+                `Moved ${report.items} items` + (report.feeds ? ` and updated ${report.feeds} feeds` : ''),
+                { title: 'Folder Relocated', kind: 'info' }
+            );
+        } catch (e) {
+            await message(String(e), { title: 'Relocate Failed', kind: 'error' });
+        }
+    };
+
+    const [excludePatterns, setExcludePatterns] = useState('');
+    const [excludeResult, setExcludeResult] = useState<string | null>(null);
+
+    useEffect(() => {
+        if (!isOpen) return;
+        invoke<string[]>('get_exclude_patterns').then(p => setExcludePatterns(p.join('\n'))).catch(console.error);
+    }, [isOpen]);
+
+    const saveExcludePatterns = async () => {
+        setExcludeResult(null);
+        try {
+            const pruned = await invoke<number>('set_exclude_patterns', { patterns: parsePatterns(excludePatterns) });
+            setExcludeResult(pruned > 0 ? `Removed ${pruned} excluded items` : 'Saved');
+            if (pruned > 0) await useAppStore.getState().fetchMedia(true);
+        } catch (e) {
+            setExcludeResult(String(e));
+        }
+    };
+
+    const [history, setHistory] = useState<HistoryStatus | null>(null);
+
+    useEffect(() => {
+        if (!isOpen) return;
+        invoke<HistoryStatus>('get_history_status').then(setHistory).catch(console.error);
+    }, [isOpen]);
+
+    const toggleHistory = async () => {
+        if (!history) return;
+        try {
+            await invoke('set_history_enabled', { enabled: !history.enabled });
+            setHistory({ ...history, enabled: !history.enabled });
+        } catch (e) {
+            await message(String(e), { title: 'View History', kind: 'error' });
+        }
+    };
+
+    const handleClearHistory = async () => {
+        const confirmed = await ask('Forget which items you have viewed, for how long, and where videos were left off? This cannot be undone.', {
+            title: 'Clear View History',
+            kind: 'warning'
+        });
+        if (!confirmed) return;
+        try {
+            await invoke('clear_history');
+            setHistory(await invoke<HistoryStatus>('get_history_status'));
+            await useAppStore.getState().fetchMedia(true);
+        } catch (e) {
+            await message(String(e), { title: 'View History', kind: 'error' });
+        }
+    };
+
+    const [formats, setFormats] = useState<FormatInfo[]>([]);
+    const [formatsError, setFormatsError] = useState<string | null>(null);
+
+    useEffect(() => {
+        if (!isOpen) return;
+        invoke<FormatInfo[]>('get_formats').then(setFormats).catch(console.error);
+    }, [isOpen]);
+
+    const toggleFormat = async (id: string) => {
+        const next = formats.map(f => f.id === id ? { ...f, enabled: !f.enabled } : f);
+        setFormatsError(null);
+        try {
+            await invoke('set_enabled_formats', { ids: next.filter(f => f.enabled).map(f => f.id) });
+            setFormats(next);
+        } catch (e) {
+            setFormatsError(String(e));
+        }
+    };
+
+    const [server, setServer] = useState<ServerStatus | null>(null);
+    const [bindAddresses, setBindAddresses] = useState('127.0.0.1');
+    const [port, setPort] = useState(8787);
+    const [serverError, setServerError] = useState<string | null>(null);
+
+    const [backups, setBackups] = useState<BackupInfo[]>([]);
+    const [backupSchedule, setBackupSchedule] = useState<BackupSchedule>({ interval_hours: 24, keep: 7 });
+    const [maintenanceBusy, setMaintenanceBusy] = useState(false);
+    const [maintenanceResult, setMaintenanceResult] = useState<string | null>(null);
+
+    const loadBackups = () => invoke<BackupInfo[]>('list_backups').then(setBackups).catch(console.error);
+
+    useEffect(() => {
+        if (!isOpen) return;
+        loadBackups();
+        invoke<BackupSchedule>('get_backup_schedule').then(setBackupSchedule).catch(console.error);
+    }, [isOpen]);
+
+    const updateBackupSchedule = async (changes: Partial<BackupSchedule>) => {
+        const next = { ...backupSchedule, ...changes };
+        setBackupSchedule(next);
+        try {
+            await invoke('set_backup_schedule', { schedule: next });
+        } catch (e) {
+            setMaintenanceResult(String(e));
+        }
+    };
+
+    // Runs one maintenance command at a time and shows its outcome under the buttons
+    const runMaintenance = async (action: () => Promise<string>) => {
+        setMaintenanceBusy(true);
+        setMaintenanceResult(null);
+        try {
+            setMaintenanceResult(await action());
+        } catch (e) {
+            setMaintenanceResult(`Failed: ${e}`);
+        } finally {
+            setMaintenanceBusy(false);
+        }
+    };
+
+    const formatSize = (bytes: number) => `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+
+    const handleBackupNow = () => runMaintenance(async () => {
+        const info = await invoke<BackupInfo>('create_backup');
+        await loadBackups();
+        return `Backup saved (${formatSize(info.size_bytes)})`;
+    });
+
+    const handleRestore = async (backup: BackupInfo) => {
+        const confirmed = await ask(
+            `Restore the library as it was on ${new Date(backup.created_at * 1000).toLocaleString()}? The current state is backed up first.`,
+            { title: 'Restore Backup', kind: 'warning' }
+        );
+        if (!confirmed) return;
+        await runMaintenance(async () => {
+            await invoke('restore_backup', { path: backup.path });
+            const { loadFolders, loadFeeds, fetchMedia } = useAppStore.getState();
+            await Promise.all([loadFolders(), loadFeeds(), loadBackups()]);
+            await fetchMedia(true);
+            return 'Backup restored';
+        });
+    };
+
+    const handleIntegrityCheck = () => runMaintenance(async () => {
+        const problems = await invoke<string[]>('check_database_integrity');
+        return problems.length === 1 && problems[0] === 'ok'
+            ? 'Integrity check passed'
+            : `Integrity check found problems:\n${problems.join('\n')}`;
+    });
+
+    const handleOptimize = () => runMaintenance(async () => {
+        const report = await invoke<{ size_before: number; size_after: number }>('optimize_database');
+        return `Optimized: ${formatSize(report.size_before)} → ${formatSize(report.size_after)}`;
+    });
+
+    const handleCleanOrphans = () => runMaintenance(async () => {
+        const report = await invoke<OrphanReport>('clean_orphans');
+        await useAppStore.getState().fetchMedia(true);
+        const kept = report.outside_folders > 0 ? `; kept ${report.outside_folders} entries outside library folders` : '';
+        return `Removed ${report.missing_files} missing files and ${report.thumbnails} stale thumbnails${kept}`;
+    });
+
+    useEffect(() => {
+        if (!isOpen) return;
+        invoke<ServerStatus>('get_http_server_status').then(status => {
+            setServer(status);
+            setBindAddresses(status.config.bind_addresses.join(', '));
+            setPort(status.config.port);
+        }).catch(e => setServerError(String(e)));
+    }, [isOpen]);
+
+    const runServerCommand = async (command: string, args?: Record<string, unknown>) => {
+        setServerError(null);
+        try {
+            setServer(await invoke<ServerStatus>(command, args));
+        } catch (e) {
+            setServerError(String(e));
+        }
+    };
+
+    const toggleServer = () => {
+        if (server?.running) return runServerCommand('stop_http_server');
+        const addresses = bindAddresses.split(',').map(a => a.trim()).filter(Boolean);
+        return runServerCommand('start_http_server', { bindAddresses: addresses, port });
+    };
+
     const handleCreateOrUpdateFeed = async () => {
         if (!newFeedName || selectedFolders.length === 0) return;
 
@@ -90,21 +485,173 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                         {/* Content */}
                         <div className="p-6 space-y-8 overflow-y-auto custom-scrollbar flex-1">
 
+                            {/* Libraries Section */}
+                            <div>
+                                <div className="flex items-center justify-between mb-3">
+                                    <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider">Libraries</h3>
+                                    <div className="flex items-center gap-3">
+                                        <button
+                                            onClick={handleOpenLibrary}
+                                            className="text-xcroller-muted hover:text-white transition-colors"
+                                            title="Open Database…"
+                                        >
+                                            <Database size={14} />
+                                        </button>
+                                        <button
+                                            onClick={() => setNewLibraryName(newLibraryName === null ? '' : null)}
+                                            className="text-xcroller-muted hover:text-white transition-colors"
+                                            title="New Library"
+                                        >
+                                            <Plus size={16} />
+                                        </button>
+                                    </div>
+                                </div>
+                                <div className="space-y-2">
+                                    {newLibraryName !== null && (
+                                        <div className="flex items-center gap-2 p-2 bg-black/20 rounded-xl border border-white/5">
+                                            <input
+                                                autoFocus
+                                                type="text"
+                                                value={newLibraryName}
+                                                onChange={(e) => setNewLibraryName(e.target.value)}
+                                                onKeyDown={(e) => e.key === 'Enter' && handleCreateLibrary()}
+                                                placeholder="Library name"
+                                                className="flex-1 bg-white/5 border border-white/10 rounded-lg px-3 py-1.5 text-sm focus:outline-none focus:border-xcroller-red"
+                                            />
+                                            <button
+                                                onClick={handleCreateLibrary}
+                                                disabled={!newLibraryName.trim()}
+                                                className="px-3 py-1.5 bg-xcroller-red text-white text-xs font-bold rounded-lg disabled:opacity-50"
+                                            >
+                                                CREATE
+                                            </button>
+                                        </div>
+                                    )}
+                                    {libraries?.libraries.map((library) => {
+                                        const isActive = library.id === libraries.active;
+                                        return (
+                                            <div key={library.id} className={`flex items-center justify-between p-3 rounded-xl transition-colors border group ${isActive ? 'bg-xcroller-red/10 border-xcroller-red/30' : 'bg-black/20 border-white/5 hover:bg-black/30'}`}>
+                                                {renamingLibrary?.id === library.id ? (
+                                                    <input
+                                                        autoFocus
+                                                        type="text"
+                                                        value={renamingLibrary.name}
+                                                        onChange={(e) => setRenamingLibrary({ id: library.id, name: e.target.value })}
+                                                        onKeyDown={(e) => {
+                                                            if (e.key === 'Enter') handleRenameLibrary();
+                                                            if (e.key === 'Escape') setRenamingLibrary(null);
+                                                        }}
+                                                        onBlur={handleRenameLibrary}
+                                                        className="flex-1 mr-2 bg-white/5 border border-white/10 rounded-lg px-2 py-1 text-sm focus:outline-none focus:border-xcroller-red"
+                                                    />
+                                                ) : (
+                                                    <button
+                                                        onClick={() => !isActive && handleSwitchLibrary(library)}
+                                                        className={`flex items-center gap-3 overflow-hidden text-left ${isActive ? 'cursor-default' : ''}`}
+                                                        title={isActive ? library.db_path : `Switch to ${library.name}`}
+                                                    >
+                                                        <LibraryIcon size={16} className={isActive ? 'text-xcroller-red shrink-0' : 'text-xcroller-muted shrink-0'} />
+                                                        <div className="flex flex-col overflow-hidden">
+                                                            <span className="text-sm font-bold text-white truncate">{library.name}</span>
+                                                            <span className="text-[10px] text-xcroller-muted truncate font-mono">{library.db_path}</span>
+                                                        </div>
+                                                    </button>
+                                                )}
+                                                <div className="flex items-center gap-1 shrink-0">
+                                                    {isActive && <Check size={14} className="text-xcroller-red mr-1" />}
+                                                    <button
+                                                        onClick={() => setRenamingLibrary({ id: library.id, name: library.name })}
+                                                        className="p-1.5 hover:bg-white/10 text-xcroller-muted hover:text-white rounded-md transition-colors opacity-0 group-hover:opacity-100"
+                                                        title="Rename Library"
+                                                    >
+                                                        <Pencil size={14} />
+                                                    </button>
+                                                    {!isActive && library.id !== 'default' && (
+                                                        <button
+                                                            onClick={() => handleDeleteLibrary(library)}
+                                                            className="p-1.5 hover:bg-red-500/20 text-xcroller-muted hover:text-red-400 rounded-md transition-colors opacity-0 group-hover:opacity-100"
+                                                            title="Delete Library"
+                                                        >
+                                                            <Trash2 size={14} />
+                                                        </button>
+                                                    )}
+                                                </div>
+                                            </div>
+                                        );
+                                    })}
+                                    {libraryError && <div className="text-xs text-red-400">{libraryError}</div>}
+                                </div>
+                            </div>
+
                             {/* Feed Management Section */}
                             <div>
                                 <div className="flex items-center justify-between mb-4">
                                     <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider">Named Side Feeds</h3>
-                                    <button
-                                        onClick={() => {
-                                            if (isCreatingFeed) resetFeedForm();
-                                            else setIsCreatingFeed(true);
-                                        }}
-                                        className="text-xs flex items-center gap-1.5 text-xcroller-red hover:underline font-bold"
-                                    >
-                                        <Plus size={14} /> {isCreatingFeed ? 'DISCARD' : 'NEW FEED'}
-                                    </button>
+                                    <div className="flex items-center gap-3">
+                                        <button
+                                            onClick={handleImportFeeds}
+                                            className="text-xcroller-muted hover:text-white transition-colors"
+                                            title="Import Feeds"
+                                        >
+                                            <Upload size={14} />
+                                        </button>
+                                        <button
+                                            onClick={handleExportFeeds}
+                                            disabled={feeds.length === 0}
+                                            className="text-xcroller-muted hover:text-white transition-colors disabled:opacity-30"
+                                            title="Export Feeds"
+                                        >
+                                            <Download size={14} />
+                                        </button>
+                                        <button
+                                            onClick={() => {
+                                                if (isCreatingFeed) resetFeedForm();
+                                                else setIsCreatingFeed(true);
+                                            }}
+                                            className="text-xs flex items-center gap-1.5 text-xcroller-red hover:underline font-bold"
+                                        >
+                                            <Plus size={14} /> {isCreatingFeed ? 'DISCARD' : 'NEW FEED'}
+                                        </button>
+                                    </div>
                                 </div>
 
+                                {pendingImport && (
+                                    <div className="mb-6 p-4 bg-white/5 rounded-xl border border-white/10 space-y-3">
+                                        <div className="text-xs text-white/90">
+                                            Importing {pendingImport.preview.feeds.length} feeds. Choose where these folders are on this machine, or leave one empty to drop it:
+                                        </div>
+                                        {Object.keys(folderMap).map(source => (
+                                            <div key={source} className="space-y-1">
+                                                <div className="text-[10px] text-xcroller-muted font-mono truncate" title={source}>{source}</div>
+                                                <select
+                                                    value={folderMap[source]}
+                                                    onChange={(e) => setFolderMap(prev => ({ ...prev, [source]: e.target.value }))}
+                                                    className="w-full bg-black/40 border border-white/10 rounded-lg p-2 text-xs outline-none focus:border-xcroller-red"
+                                                >
+                                                    <option value="">Don't include</option>
+                                                    {folderPaths.map(f => (
+                                                        <option key={f.id} value={f.path}>{f.path}</option>
+                                                    ))}
+                                                </select>
+                                            </div>
+                                        ))}
+                                        <div className="flex gap-2">
+                                            <button
+                                                onClick={() => setPendingImport(null)}
+                                                className="flex-1 py-2 bg-white/5 text-white text-xs font-bold rounded-lg hover:bg-white/10"
+                                            >
+                                                CANCEL
+                                            </button>
+                                            <button
+                                                onClick={() => runImport(pendingImport.path, folderMap)}
+                                                className="flex-[2] py-2 bg-xcroller-red text-white text-xs font-bold rounded-lg"
+                                            >
+                                                IMPORT FEEDS
+                                            </button>
+                                        </div>
+                                    </div>
+                                )}
+
                                 <AnimatePresence>
                                     {isCreatingFeed && (
                                         <motion.div
@@ -210,24 +757,277 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                                         <div className="text-sm text-xcroller-muted italic py-2">No folders added yet.</div>
                                     ) : (
                                         folderPaths.map((folder) => (
-                                            <div key={folder.id} className="flex items-center justify-between p-3 bg-black/20 rounded-xl hover:bg-black/30 transition-colors group border border-white/5">
-                                                <div className="flex items-center gap-3 overflow-hidden">
+                                            <div key={folder.id} className="bg-black/20 rounded-xl border border-white/5">
+                                            <div className="flex items-center justify-between p-3 hover:bg-black/30 rounded-xl transition-colors group">
+                                                <div className={`flex items-center gap-3 overflow-hidden ${folder.is_active ? '' : 'opacity-40'}`}>
                                                     <FolderIcon size={16} className="text-xcroller-muted group-hover:text-xcroller-red transition-colors shrink-0" />
                                                     <span className="text-sm text-white/90 truncate font-mono text-[11px]" title={folder.path}>{folder.path}</span>
                                                 </div>
-                                                <button
-                                                    onClick={() => removeFolder(folder.path)}
-                                                    className="p-1.5 hover:bg-red-500/20 text-xcroller-muted hover:text-red-400 rounded-md transition-colors opacity-0 group-hover:opacity-100"
-                                                    title="Remove Folder"
-                                                >
-                                                    <Trash2 size={16} />
-                                                </button>
+                                                <div className="flex items-center gap-1 shrink-0">
+                                                    <button
+                                                        onClick={() => setExpandedFolderId(expandedFolderId === folder.id ? null : folder.id)}
+                                                        className={`p-1.5 hover:bg-white/10 hover:text-white rounded-md transition-colors ${expandedFolderId === folder.id ? 'text-white' : 'text-xcroller-muted opacity-0 group-hover:opacity-100'}`}
+                                                        title="Folder Settings"
+                                                    >
+                                                        <SlidersHorizontal size={16} />
+                                                    </button>
+                                                    <button
+                                                        onClick={() => handleRelocateFolder(folder.path)}
+                                                        className="p-1.5 hover:bg-white/10 text-xcroller-muted hover:text-white rounded-md transition-colors opacity-0 group-hover:opacity-100"
+                                                        title="Relocate Folder"
+                                                    >
+                                                        <FolderSync size={16} />
+                                                    </button>
+                                                    <button
+                                                        onClick={() => removeFolder(folder.path)}
+                                                        className="p-1.5 hover:bg-red-500/20 text-xcroller-muted hover:text-red-400 rounded-md transition-colors opacity-0 group-hover:opacity-100"
+                                                        title="Remove Folder"
+                                                    >
+                                                        <Trash2 size={16} />
+                                                    </button>
+                                                </div>
+                                            </div>
+                                            {expandedFolderId === folder.id && (
+                                                <div className="px-4 pb-4 pt-1 grid grid-cols-2 gap-x-6 gap-y-3">
+                                                    {([
+                                                        { key: 'is_active', label: 'Show in library' },
+                                                        { key: 'recursive', label: 'Include subfolders' },
+                                                        { key: 'include_hidden', label: 'Hidden files' },
+                                                        { key: 'follow_symlinks', label: 'Follow symlinks' },
+                                                    ] as const).map(option => (
+                                                        <label key={option.key} className="flex items-center justify-between gap-2 text-xs text-white/80">
+                                                            {option.label}
+                                                            <input
+                                                                type="checkbox"
+                                                                checked={folder[option.key]}
+                                                                onChange={(e) => changeFolderSettings(folder, { [option.key]: e.target.checked })}
+                                                                className="accent-xcroller-red"
+                                                            />
+                                                        </label>
+                                                    ))}
+                                                    <label className="flex items-center justify-between gap-2 text-xs text-white/80">
+                                                        Max depth
+                                                        <input
+                                                            type="number"
+                                                            min={0}
+                                                            placeholder="Any"
+                                                            disabled={!folder.recursive}
+                                                            value={folder.max_depth ?? ''}
+                                                            onChange={(e) => changeFolderSettings(folder, { max_depth: e.target.value === '' ? null : Math.max(0, parseInt(e.target.value)) })}
+                                                            className="w-16 bg-white/5 border border-white/10 rounded px-2 py-1 text-xs disabled:opacity-40"
+                                                        />
+                                                    </label>
+                                                    <label className="flex items-center justify-between gap-2 text-xs text-white/80">
+                                                        Auto-rescan
+                                                        <select
+                                                            value={folder.rescan_interval_min ?? ''}
+                                                            onChange={(e) => changeFolderSettings(folder, { rescan_interval_min: e.target.value === '' ? null : Number(e.target.value) })}
+                                                            className="bg-white/5 border border-white/10 rounded px-2 py-1 text-xs"
+                                                        >
+                                                            <option value="">Off</option>
+                                                            <option value="15">Every 15 min</option>
+                                                            <option value="60">Hourly</option>
+                                                            <option value="360">Every 6 h</option>
+                                                            <option value="1440">Daily</option>
+                                                        </select>
+                                                    </label>
+                                                    <label className="col-span-2 flex flex-col gap-1 text-xs text-white/80">
+                                                        Exclude (one pattern per line, also read from .xcrollerignore files)
+                                                        <textarea
+                                                            rows={3}
+                                                            defaultValue={folder.exclude_patterns.join('\n')}
+                                                            onBlur={(e) => {
+                                                                const patterns = parsePatterns(e.target.value);
+                                                                if (patterns.join('\n') !== folder.exclude_patterns.join('\n')) {
+                                                                    changeFolderSettings(folder, { exclude_patterns: patterns });
+                                                                }
+                                                            }}
+                                                            placeholder={'cache/\n*.tmp.png'}
+                                                            className="bg-white/5 border border-white/10 rounded px-2 py-1 text-[11px] font-mono resize-y"
+                                                        />
+                                                    </label>
+                                                    <div className="col-span-2 text-[10px] text-xcroller-muted">
+                                                        {folder.last_scanned_at
+                                                            ? `Last scanned ${new Date(folder.last_scanned_at * 1000).toLocaleString()}`
+                                                            : 'Not scanned since these settings were added'}
+                                                        {folderError && <span className="text-red-400 ml-2">{folderError}</span>}
+                                                    </div>
+                                                </div>
+                                            )}
                                             </div>
                                         ))
                                     )}
                                 </div>
                             </div>
 
+                            {/* LAN Access Section */}
+                            <div>
+                                <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider mb-3">LAN Access</h3>
+                                <div className="flex flex-col gap-3 p-4 bg-black/20 rounded-xl border border-white/5">
+                                    <div className="flex items-center justify-between">
+                                        <div className="flex flex-col">
+                                            <span className="text-sm text-white/90 font-bold flex items-center gap-2"><Wifi size={14} /> Web Server</span>
+                                            <span className="text-[10px] text-xcroller-muted">Browse this library from other devices on your network</span>
+                                        </div>
+                                        <button
+                                            onClick={toggleServer}
+                                            className={`w-12 h-6 rounded-full transition-colors relative ${server?.running ? 'bg-xcroller-red' : 'bg-white/10'}`}
+                                        >
+                                            <div className={`absolute top-1 w-4 h-4 bg-white rounded-full transition-transform ${server?.running ? 'left-7' : 'left-1'}`} />
+                                        </button>
+                                    </div>
+                                    <div className="flex gap-2">
+                                        <input
+                                            type="text"
+                                            value={bindAddresses}
+                                            onChange={(e) => setBindAddresses(e.target.value)}
+                                            disabled={server?.running}
+                                            placeholder="127.0.0.1, 192.168.1.20"
+                                            title="Interfaces to listen on, comma separated. 0.0.0.0 listens on all of them."
+                                            className="flex-1 bg-black/40 border border-white/10 rounded-lg p-2 text-xs font-mono outline-none focus:border-xcroller-red disabled:opacity-50"
+                                        />
+                                        <input
+                                            type="number"
+                                            min={1}
+                                            max={65535}
+                                            value={port}
+                                            onChange={(e) => setPort(parseInt(e.target.value) || 0)}
+                                            disabled={server?.running}
+                                            className="w-20 bg-black/40 border border-white/10 rounded-lg p-2 text-xs font-mono outline-none focus:border-xcroller-red disabled:opacity-50"
+                                        />
+                                    </div>
+                                    {server && (
+                                        <div className="flex items-center justify-between gap-2">
+                                            <span className="text-[10px] text-xcroller-muted truncate">
+                                                Token: <span className="font-mono text-white/80 select-all">{server.config.token}</span>
+                                            </span>
+                                            <button
+                                                onClick={() => runServerCommand('regenerate_http_token')}
+                                                className="p-1.5 hover:bg-white/10 text-xcroller-muted hover:text-white rounded-lg transition-colors shrink-0"
+                                                title="New token (signs out every browser)"
+                                            >
+                                                <RefreshCw size={12} />
+                                            </button>
+                                        </div>
+                                    )}
+                                    {server?.running && server.urls.map(url => (
+                                        <span key={url} className="text-[11px] font-mono text-xcroller-red select-all">{url}</span>
+                                    ))}
+                                    {serverError && <span className="text-[11px] text-red-400">{serverError}</span>}
+                                </div>
+                            </div>
+
+                            {/* Library Health Section */}
+                            <div>
+                                <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider mb-3">Library Health</h3>
+                                <div className="flex flex-col gap-3 p-4 bg-black/20 rounded-xl border border-white/5">
+                                    <div className="flex gap-2">
+                                        <button
+                                            onClick={handleHealthCheck}
+                                            disabled={healthBusy}
+                                            className="flex-1 py-2 bg-white/5 text-white text-[11px] font-bold rounded-lg hover:bg-white/10 disabled:opacity-50 transition-colors flex items-center justify-center gap-2"
+                                        >
+                                            <HeartPulse size={14} /> {healthBusy ? 'CHECKING…' : 'CHECK FILES'}
+                                        </button>
+                                        {healthIssues?.some(i => i.status === 'missing' || i.status === 'offline') && (
+                                            <button
+                                                onClick={handleRelink}
+                                                disabled={healthBusy}
+                                                className="flex-1 py-2 bg-xcroller-red text-white text-[11px] font-bold rounded-lg disabled:opacity-50"
+                                                title="Find moved files by name, size and content under a folder"
+                                            >
+                                                RELINK…
+                                            </button>
+                                        )}
+                                    </div>
+                                    {healthIssues && (
+                                        healthIssues.length === 0 ? (
+                                            <span className="text-[11px] text-white/80">Every file is present and readable.</span>
+                                        ) : (
+                                            <div className="space-y-1 max-h-40 overflow-y-auto pr-1 custom-scrollbar">
+                                                {healthIssues.map(issue => (
+                                                    <div key={issue.id} className="flex items-center gap-2 text-[11px]" title={issue.message ?? issue.path}>
+                                                        <span className={`shrink-0 uppercase font-bold text-[9px] px-1.5 py-0.5 rounded ${issue.status === 'offline' ? 'bg-white/10 text-white/60' : 'bg-red-500/20 text-red-300'}`}>
+                                                            {issue.status}
+                                                        </span>
+                                                        <span className="truncate font-mono text-white/70">{issue.path}</span>
+                                                    </div>
+                                                ))}
+                                            </div>
+                                        )
+                                    )}
+                                    {healthResult && <span className="text-[11px] text-white/80">{healthResult}</span>}
+                                </div>
+                            </div>
+
+                            {/* Database Section */}
+                            <div>
+                                <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider mb-3">Database</h3>
+                                <div className="flex flex-col gap-3 p-4 bg-black/20 rounded-xl border border-white/5">
+                                    <div className="flex items-center justify-between gap-2 text-xs text-white/90">
+                                        <span className="flex items-center gap-2 font-bold"><Database size={14} /> Back up every</span>
+                                        <div className="flex items-center gap-2">
+                                            <select
+                                                value={backupSchedule.interval_hours}
+                                                onChange={(e) => updateBackupSchedule({ interval_hours: parseInt(e.target.value) })}
+                                                className="bg-black/40 border border-white/10 rounded-lg p-1.5 text-xs outline-none focus:border-xcroller-red"
+                                            >
+                                                <option value={0}>Never</option>
+                                                <option value={6}>6 hours</option>
+                                                <option value={24}>Day</option>
+                                                <option value={168}>Week</option>
+                                            </select>
+                                            <span className="text-xcroller-muted">keep</span>
+                                            <input
+                                                type="number"
+                                                min={1}
+                                                max={100}
+                                                value={backupSchedule.keep}
+                                                onChange={(e) => updateBackupSchedule({ keep: Math.max(1, parseInt(e.target.value) || 1) })}
+                                                className="w-14 bg-black/40 border border-white/10 rounded-lg p-1.5 text-xs font-mono outline-none focus:border-xcroller-red"
+                                            />
+                                        </div>
+                                    </div>
+                                    {backups.length > 0 && (
+                                        <div className="space-y-1 max-h-32 overflow-y-auto pr-1 custom-scrollbar">
+                                            {backups.map(b => (
+                                                <div key={b.path} className="flex items-center justify-between text-[11px] text-white/70 group">
+                                                    <span>{new Date(b.created_at * 1000).toLocaleString()} · {formatSize(b.size_bytes)}</span>
+                                                    <button
+                                                        onClick={() => handleRestore(b)}
+                                                        disabled={maintenanceBusy}
+                                                        className="p-1 hover:bg-white/10 text-xcroller-muted hover:text-white rounded transition-colors opacity-0 group-hover:opacity-100"
+                                                        title="Restore this backup"
+                                                    >
+                                                        <RotateCcw size={12} />
+                                                    </button>
+                                                </div>
+                                            ))}
+                                        </div>
+                                    )}
+                                    <div className="grid grid-cols-2 gap-2">
+                                        {([
+                                            ['BACK UP NOW', handleBackupNow],
+                                            ['CHECK INTEGRITY', handleIntegrityCheck],
+                                            ['OPTIMIZE', handleOptimize],
+                                            ['CLEAN ORPHANS', handleCleanOrphans]
+                                        ] as [string, () => void][]).map(([label, action]) => (
+                                            <button
+                                                key={label}
+                                                onClick={action}
+                                                disabled={maintenanceBusy}
+                                                className="py-2 bg-white/5 text-white text-[11px] font-bold rounded-lg hover:bg-white/10 disabled:opacity-50 transition-colors"
+                                            >
+                                                {label}
+                                            </button>
+                                        ))}
+                                    </div>
+                                    {maintenanceResult && (
+                                        <span className="text-[11px] text-white/80 whitespace-pre-line">{maintenanceResult}</span>
+                                    )}
+                                </div>
+                            </div>
+
                             {/* Preferences Section */}
                             <div>
                                 <h3 className="text-sm font-medium text-xcroller-muted uppercase tracking-wider mb-3">System Preferences</h3>
@@ -265,6 +1065,68 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                                             </button>
                                         </div>
                                     </div>
+                                    <div className="flex flex-col gap-2 p-4 bg-black/20 rounded-xl border border-white/5">
+                                        <div className="flex items-center justify-between">
+                                            <div className="flex flex-col">
+                                                <span className="text-sm text-white/90 font-bold">View History</span>
+                                                <span className="text-[10px] text-xcroller-muted">
+                                                    {history
+                                                        ? `${history.viewed_items} items viewed, ${Math.round(history.total_watch_time_sec / 60)} min of video watched`
+                                                        : 'Powers the Recent and Continue feeds and the unseen filters'}
+                                                </span>
+                                            </div>
+                                            <button
+                                                onClick={toggleHistory}
+                                                className={`w-12 h-6 rounded-full transition-colors relative ${history?.enabled ? 'bg-xcroller-red' : 'bg-white/10'}`}
+                                                title={history?.enabled ? 'Stop recording views' : 'Record views'}
+                                            >
+                                                <div className={`absolute top-1 w-4 h-4 bg-white rounded-full transition-transform ${history?.enabled ? 'left-7' : 'left-1'}`} />
+                                            </button>
+                                        </div>
+                                        <button
+                                            onClick={handleClearHistory}
+                                            disabled={!history || history.viewed_items === 0}
+                                            className="self-start text-[11px] font-bold text-xcroller-muted hover:text-red-400 transition-colors disabled:opacity-30"
+                                        >
+                                            CLEAR HISTORY
+                                        </button>
+                                    </div>
+                                    <div className="flex flex-col gap-2 p-4 bg-black/20 rounded-xl border border-white/5">
+                                        <div className="flex flex-col">
+                                            <span className="text-sm text-white/90 font-bold">File Formats</span>
+                                            <span className="text-[10px] text-xcroller-muted">Picked up by the next scan; files are recognised by content, not just extension</span>
+                                        </div>
+                                        {(['image', 'video'] as const).map(kind => (
+                                            <div key={kind} className="flex flex-wrap gap-1.5">
+                                                {formats.filter(f => f.kind === kind).map(f => (
+                                                    <button
+                                                        key={f.id}
+                                                        onClick={() => toggleFormat(f.id)}
+                                                        title={f.extensions.map(e => `.${e}`).join(' ')}
+                                                        className={`px-2 py-1 rounded-md text-[10px] font-bold uppercase transition-colors ${f.enabled ? 'bg-xcroller-red text-white' : 'bg-white/5 text-white/40 hover:bg-white/10'}`}
+                                                    >
+                                                        {f.extensions[0]}
+                                                    </button>
+                                                ))}
+                                            </div>
+                                        ))}
+                                        {formatsError && <span className="text-[11px] text-red-300">{formatsError}</span>}
+                                    </div>
+                                    <div className="flex flex-col gap-2 p-4 bg-black/20 rounded-xl border border-white/5">
+                                        <div className="flex flex-col">
+                                            <span className="text-sm text-white/90 font-bold">Excluded Everywhere</span>
+                                            <span className="text-[10px] text-xcroller-muted">gitignore-style patterns for every folder; matching items are removed from the library</span>
+                                        </div>
+                                        <textarea
+                                            rows={4}
+                                            value={excludePatterns}
+                                            onChange={(e) => setExcludePatterns(e.target.value)}
+                                            onBlur={saveExcludePatterns}
+                                            placeholder={'node_modules/\n.cache/\n*_render_tmp.*'}
+                                            className="bg-white/5 border border-white/10 rounded-lg px-3 py-2 text-[11px] font-mono resize-y"
+                                        />
+                                        {excludeResult && <span className="text-[11px] text-xcroller-muted">{excludeResult}</span>}
+                                    </div>
                                 </div>
                             </div>
 
@@ -1,7 +1,7 @@
 import { useState, useEffect } from 'react';
 import { useAppStore } from '../lib/store';
 import { motion, AnimatePresence } from 'framer-motion';
-import { Minus, Plus, Grid3X3, FolderPlus, Play, Pause, Settings, Volume2, Maximize, Minimize, Filter, RefreshCcw, Star, Download, Trash2 } from 'lucide-react';
+import { Minus, Plus, Grid3X3, FolderPlus, Play, Pause, Settings, Volume2, Maximize, Minimize, Filter, RefreshCcw, Star, Download, Trash2, History } from 'lucide-react';
 import { open, message, ask } from '@tauri-apps/plugin-dialog';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { SettingsModal } from './SettingsModal';
@@ -138,8 +138,15 @@ export function Toolbar() {
             });
 
             if (selected && typeof selected === 'string') {
-                const count = await exportFavorites(selected);
-                await message(`Successfully exported ${count} favorites to ${selected}`, { title: 'Export Complete', kind: 'info' });
+                const report = await exportFavorites(selected);
+                const details = [
+                    report.skipped > 0 ? `${report.skipped} skipped` : null,
+                    report.failed > 0 ? `${report.failed} failed` : null
+                ].filter(Boolean).join(', ');
+                await message(
+                    `Exported ${report.exported} favorites to ${selected}${details ? ` (${details})` : ''}`,
+                    { title: 'Export Complete', kind: report.failed > 0 ? 'warning' : 'info' }
+                );
             }
         } catch (err) {
             console.error(err);
@@ -208,6 +215,24 @@ export function Toolbar() {
                                 <Star size={12} className={activeFeedId === 'favorites' ? 'fill-current' : ''} />
                                 FAVORITES
                             </button>
+                            <button
+                                onClick={() => setActiveFeed('recent')}
+                                className={`px-4 py-1.5 rounded-lg text-xs font-bold uppercase tracking-wider transition-all whitespace-nowrap flex items-center gap-1 ${activeFeedId === 'recent' ? 'bg-xcroller-red text-white shadow-lg' : 'text-xcroller-muted hover:text-white hover:bg-white/5'
+                                    }`}
+                                title="Recently Viewed"
+                            >
+                                <History size={12} />
+                                RECENT
+                            </button>
+                            <button
+                                onClick={() => setActiveFeed('continue')}
+                                className={`px-4 py-1.5 rounded-lg text-xs font-bold uppercase tracking-wider transition-all whitespace-nowrap flex items-center gap-1 ${activeFeedId === 'continue' ? 'bg-xcroller-red text-white shadow-lg' : 'text-xcroller-muted hover:text-white hover:bg-white/5'
+                                    }`}
+                                title="Continue Watching"
+                            >
+                                <Play size={12} />
+                                CONTINUE
+                            </button>
                             {feeds.map(feed => (
                                 <button
                                     key={feed.id}
@@ -0,0 +1,96 @@
+// Minimal BlurHash decoder (https://blurha.sh) for tile placeholders.
+
+const CHARS = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+const SIZE = 32;
+
+const decode83 = (s: string) => [...s].reduce((acc, c) => acc * 83 + CHARS.indexOf(c), 0);
+
+const srgbToLinear = (v: number) => {
+    const x = v / 255;
+    return x <= 0.04045 ? x / 12.92 : Math.pow((x + 0.055) / 1.055, 2.4);
+};
+
+const linearToSrgb = (v: number) => {
+    const x = Math.max(0, Math.min(1, v));
+    return Math.round((x <= 0.0031308 ? x * 12.92 : 1.055 * Math.pow(x, 1 / 2.4) - 0.055) * 255);
+};
+
+const signPow = (v: number, exp: number) => Math.sign(v) * Math.pow(Math.abs(v), exp);
+
+const cache = new Map<string, string>();
+
+/** Renders `hash` to a small PNG data URL; stretched by CSS, it looks the same at any size. */
+export function blurhashToDataUrl(hash: string): string | null {
+    const cached = cache.get(hash);
+    if (cached) return cached;
+    if (hash.length < 6) return null;
+
+    const sizeFlag = decode83(hash[0]);
+    const nx = (sizeFlag % 9) + 1;
+    const ny = Math.floor(sizeFlag / 9) + 1;
+    if (hash.length !== 4 + 2 * nx * ny) return null;
+    const maxValue = (decode83(hash[1]) + 1) / 166;
+
+    const colors: [number, number, number][] = [];
+    const dc = decode83(hash.slice(2, 6));
+    colors.push([srgbToLinear(dc >> 16), srgbToLinear((dc >> 8) & 255), srgbToLinear(dc & 255)]);
+    for (let i = 1; i < nx * ny; i++) {
+        const ac = decode83(hash.slice(4 + i * 2, 6 + i * 2));
+        colors.push([
+            signPow((Math.floor(ac / (19 * 19)) - 9) / 9, 2) * maxValue,
+            signPow(((Math.floor(ac / 19) % 19) - 9) / 9, 2) * maxValue,
+            signPow(((ac % 19) - 9) / 9, 2) * maxValue,
+        ]);
+    }
+
+    const canvas = document.createElement('canvas');
+    canvas.width = SIZE;
+    canvas.height = SIZE;
+    const ctx = canvas.getContext('2d');
+    if (!ctx) return null;
+    const image = ctx.createImageData(SIZE, SIZE);
+    for (let y = 0; y < SIZE; y++) {
+        for (let x = 0; x < SIZE; x++) {
+            let r = 0, g = 0, b = 0;
+            for (let j = 0; j < ny; j++) {
+                for (let i = 0; i < nx; i++) {
+                    const basis = Math.cos((Math.PI * x * i) / SIZE) * Math.cos((Math.PI * y * j) / SIZE);
+                    const color = colors[i + j * nx];
+                    r += color[0] * basis;
+                    g += color[1] * basis;
+                    b += color[2] * basis;
+                }
+            }
+            const p = 4 * (x + y * SIZE);
+            image.data[p] = linearToSrgb(r);
+            image.data[p + 1] = linearToSrgb(g);
+            image.data[p + 2] = linearToSrgb(b);
+            image.data[p + 3] = 255;
+        }
+    }
+    ctx.putImageData(image, 0, 0);
+
+    const url = canvas.toDataURL();
+    cache.set(hash, url);
+    return url;
+}
+
+/** Samples a video frame small enough to send to the backend for its placeholder. */
+export function sampleVideoFrame(video: HTMLVideoElement): { width: number; height: number; rgba: number[] } | null {
+    if (!video.videoWidth || !video.videoHeight) return null;
+    const scale = SIZE / Math.max(video.videoWidth, video.videoHeight);
+    const width = Math.max(1, Math.round(video.videoWidth * scale));
+    const height = Math.max(1, Math.round(video.videoHeight * scale));
+    const canvas = document.createElement('canvas');
+    canvas.width = width;
+    canvas.height = height;
+    const ctx = canvas.getContext('2d');
+    if (!ctx) return null;
+    try {
+        ctx.drawImage(video, 0, 0, width, height);
+        return { width, height, rgba: Array.from(ctx.getImageData(0, 0, width, height).data) };
+    } catch {
+        // Tainted canvas or a frame that isn't ready
+        return null;
+    }
+}
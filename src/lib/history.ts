@@ -0,0 +1,30 @@
+import { useAppStore } from './store';
+
+// Anything shorter is scrolling or skipping past, not viewing
+const MIN_VIEW_MS = 1000;
+
+export interface ViewTracker {
+    onTimeUpdate: (currentTime: number) => void;
+    end: () => void;
+}
+
+/** Tracks one item on screen; `end` records the view if it stayed up long enough. */
+export function trackView(id: number): ViewTracker {
+    const shownAt = Date.now();
+    let watched = 0;
+    let lastTime: number | null = null;
+    return {
+        // Fed from a video's timeupdate; seeks and loop restarts don't count as watch time
+        onTimeUpdate: (currentTime) => {
+            if (lastTime !== null && currentTime > lastTime && currentTime - lastTime < 2) {
+                watched += currentTime - lastTime;
+            }
+            lastTime = currentTime;
+        },
+        end: () => {
+            if (Date.now() - shownAt >= MIN_VIEW_MS) {
+                useAppStore.getState().recordView(id, watched);
+            }
+        },
+    };
+}
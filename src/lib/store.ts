@@ -1,11 +1,14 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
-import { MediaItem, Folder, FilterOptions, Feed } from './types';
+import { MediaItem, Folder, FolderNode, FolderSettings, FilterOptions, Feed, CollisionPolicy, ExportReport, ExportSource, ExportOptions, PlaylistOptions, TrashReport, PathChange, FeedImportPreview, LibraryStats, HealthIssue, RelinkReport, RemapReport, LibraryRegistry, Playback } from './types';
 
 interface AppState {
     mediaItems: MediaItem[];
     columns: number;
     folderPaths: Folder[];
+    folderTree: FolderNode[];
+    libraries: LibraryRegistry | null;
+    browsePath: string | null; // Subfolder the home feed is narrowed to
     isLoading: boolean;
     selectedMediaId: number | null;
     isAutoScrolling: boolean;
@@ -16,9 +19,11 @@ interface AppState {
     isFullscreen: boolean;
     filters: FilterOptions;
     feeds: Feed[];
-    activeFeedId: number | 'home' | 'favorites';
+    activeFeedId: number | 'home' | 'favorites' | 'recent' | 'continue';
 
     hasMore: boolean;
+    shuffleSeed: number;
+    shuffleAsOf: number; // Unix seconds; views after this don't reweigh the current shuffle
 
     // Actions
     setColumns: (cols: number) => void;
@@ -32,18 +37,40 @@ interface AppState {
     setIsHoverPaused: (status: boolean) => void;
     toggleAutoScroll: () => void;
     updateItemDimensions: (id: number, width: number, height: number) => void;
+    loadLibraries: () => Promise<void>;
+    switchLibrary: (id: string) => Promise<void>;
     loadFolders: () => Promise<void>;
+    loadFolderTree: () => Promise<void>;
+    setBrowsePath: (path: string | null) => void;
     addFolder: (path: string, recursive?: boolean) => Promise<void>;
     removeFolder: (path: string) => Promise<void>;
+    remapFolder: (oldRoot: string, newRoot: string) => Promise<RemapReport>;
+    updateFolderSettings: (path: string, settings: FolderSettings) => Promise<number>;
     fetchMedia: (reset?: boolean) => Promise<void>;
     toggleStar: (id: number) => void;
+    recordView: (id: number, watchTimeSec?: number) => void;
+    savePlaybackPosition: (id: number, positionSec: number, durationSec?: number) => void;
+    deleteMedia: (ids: number[]) => Promise<number[]>;
+    restoreMedia: (ids: number[]) => Promise<TrashReport>;
+    renameMedia: (id: number, newName: string) => Promise<void>;
+    batchRenameMedia: (ids: number[], template: string, start?: number) => Promise<void>;
+    moveMedia: (ids: number[], targetFolder: string) => Promise<void>;
+    applyPathChanges: (changes: PathChange[]) => void;
+    getLibraryStats: () => Promise<LibraryStats>;
+    checkLibraryHealth: (deep?: boolean) => Promise<HealthIssue[]>;
+    relinkMedia: (ids: number[], searchFolder: string) => Promise<RelinkReport>;
 
     // Feed Actions
-    setActiveFeed: (feedId: number | 'home' | 'favorites') => void;
+    setActiveFeed: (feedId: number | 'home' | 'favorites' | 'recent' | 'continue') => void;
     loadFeeds: () => Promise<void>;
     saveFeed: (feed: Feed) => Promise<void>;
     deleteFeed: (id: number) => Promise<void>;
-    exportFavorites: (targetPath: string) => Promise<number>;
+    exportFeeds: (targetPath: string, ids?: number[]) => Promise<number>;
+    inspectFeedImport: (path: string) => Promise<FeedImportPreview>;
+    importFeeds: (path: string, folderMap?: Record<string, string>) => Promise<number>;
+    exportFavorites: (targetPath: string, collision?: CollisionPolicy) => Promise<ExportReport>;
+    exportMedia: (source: ExportSource, targetPath: string, options?: ExportOptions) => Promise<ExportReport>;
+    exportPlaylist: (source: ExportSource, targetPath: string, options: PlaylistOptions) => Promise<number>;
     clearFavorites: () => Promise<void>;
 }
 
@@ -51,6 +78,9 @@ export const useAppStore = create<AppState>((set, get) => ({
     mediaItems: [],
     columns: 5,
     folderPaths: [],
+    folderTree: [],
+    libraries: null,
+    browsePath: null,
     isLoading: false,
     selectedMediaId: null,
     isAutoScrolling: false,
@@ -68,6 +98,8 @@ export const useAppStore = create<AppState>((set, get) => ({
     feeds: [],
     activeFeedId: 'home',
     hasMore: true,
+    shuffleSeed: 0,
+    shuffleAsOf: 0,
 
     setColumns: (cols) => set({ columns: cols }),
     setSelectedMediaId: (id) => set({ selectedMediaId: id }),
@@ -80,7 +112,7 @@ export const useAppStore = create<AppState>((set, get) => ({
         set({ filters: currentFilters, mediaItems: [], hasMore: true });
 
         const { activeFeedId, feeds, saveFeed } = get();
-        if (activeFeedId !== 'home' && activeFeedId !== 'favorites') {
+        if (typeof activeFeedId === 'number') {
             const feed = feeds.find(f => f.id === activeFeedId);
             if (feed) {
                 await saveFeed({
@@ -104,6 +136,22 @@ export const useAppStore = create<AppState>((set, get) => ({
         }));
     },
 
+    loadLibraries: async () => {
+        try {
+            set({ libraries: await invoke<LibraryRegistry>('get_libraries') });
+        } catch (e) {
+            console.error("Failed to load libraries", e);
+        }
+    },
+
+    switchLibrary: async (id) => {
+        await invoke('switch_library', { id });
+        // Nothing from the previous library carries over
+        set({ selectedMediaId: null, feeds: [], folderPaths: [], folderTree: [] });
+        get().setActiveFeed('home');
+        await Promise.all([get().loadLibraries(), get().loadFolders(), get().loadFolderTree(), get().loadFeeds()]);
+    },
+
     loadFolders: async () => {
         try {
             const folders = await invoke<Folder[]>('get_folders');
@@ -119,6 +167,19 @@ export const useAppStore = create<AppState>((set, get) => ({
         }
     },
 
+    loadFolderTree: async () => {
+        try {
+            set({ folderTree: await invoke<FolderNode[]>('get_folder_tree') });
+        } catch (e) {
+            console.error("Failed to load folder tree", e);
+        }
+    },
+
+    setBrowsePath: (path) => {
+        set({ browsePath: path, activeFeedId: 'home', mediaItems: [], hasMore: true });
+        get().fetchMedia(true);
+    },
+
     addFolder: async (path, recursive) => {
         set({ isLoading: true });
         try {
@@ -146,6 +207,20 @@ export const useAppStore = create<AppState>((set, get) => ({
         }
     },
 
+    updateFolderSettings: async (path, settings) => {
+        const pruned = await invoke<number>('update_folder_settings', { path, settings });
+        await get().loadFolders();
+        await get().fetchMedia(true);
+        return pruned;
+    },
+
+    remapFolder: async (oldRoot, newRoot) => {
+        const report = await invoke<RemapReport>('remap_folder', { oldRoot, newRoot });
+        await Promise.all([get().loadFolders(), get().loadFeeds()]);
+        await get().fetchMedia(true);
+        return report;
+    },
+
     fetchMedia: async (reset = false) => {
         if (get().isLoading && !reset) return;
         if (!get().hasMore && !reset) return;
@@ -153,11 +228,19 @@ export const useAppStore = create<AppState>((set, get) => ({
         set({ isLoading: true });
         const limit = 50;
         const offset = reset ? 0 : get().mediaItems.length;
-        const { activeFeedId, feeds, folderPaths, filters } = get();
-        let queryFilters = { ...filters };
+        // A fresh shuffle on every reload, the same one for every page after it
+        if (reset) set({ shuffleSeed: Math.floor(Math.random() * 2 ** 31), shuffleAsOf: Math.floor(Date.now() / 1000) });
+        const { activeFeedId, feeds, folderPaths, browsePath, filters, shuffleSeed, shuffleAsOf } = get();
+        let queryFilters = { ...filters, shuffle_seed: shuffleSeed, shuffle_as_of: shuffleAsOf };
 
         if (activeFeedId === 'favorites') {
             queryFilters.favorites_only = true;
+        } else if (activeFeedId === 'recent') {
+            queryFilters.unseen = false;
+            queryFilters.folder_paths = folderPaths.filter(f => f.is_active).map(f => f.path);
+        } else if (activeFeedId === 'continue') {
+            queryFilters.in_progress = true;
+            queryFilters.folder_paths = folderPaths.filter(f => f.is_active).map(f => f.path);
         } else if (activeFeedId !== 'home') {
             const feed = feeds.find(f => f.id === activeFeedId);
             if (feed) {
@@ -166,18 +249,24 @@ export const useAppStore = create<AppState>((set, get) => ({
             }
         } else {
             // Home feed
-            queryFilters.folder_paths = folderPaths.filter(f => f.is_active).map(f => f.path);
+            queryFilters.folder_paths = browsePath
+                ? [browsePath]
+                : folderPaths.filter(f => f.is_active).map(f => f.path);
         }
 
         try {
             const newItems = await invoke<MediaItem[]>('get_media', { limit, offset, filters: queryFilters });
-            set((state) => ({
-                mediaItems: reset ? newItems : [...state.mediaItems, ...newItems],
-                hasMore: newItems.length === limit,
-                isLoading: false
-            }));
+            set((state) => {
+                // Items scanned or viewed since the last page can shift other sorts and filters
+                const seen = new Set(state.mediaItems.map(item => item.id));
+                return {
+                    mediaItems: reset ? newItems : [...state.mediaItems, ...newItems.filter(item => !seen.has(item.id))],
+                    hasMore: newItems.length === limit,
+                    isLoading: false
+                };
+            });
 
-            if (reset && newItems.length === 0 && queryFilters.folder_paths && queryFilters.folder_paths.length > 0 && activeFeedId !== 'favorites') {
+            if (reset && newItems.length === 0 && queryFilters.folder_paths && queryFilters.folder_paths.length > 0 && activeFeedId !== 'favorites' && activeFeedId !== 'recent' && activeFeedId !== 'continue') {
                 // Retry once if empty on start
                 setTimeout(() => {
                     const currentItems = get().mediaItems;
@@ -205,8 +294,99 @@ export const useAppStore = create<AppState>((set, get) => ({
         }
     },
 
+    recordView: async (id, watchTimeSec) => {
+        try {
+            const recorded = await invoke<boolean>('record_view', { id, watchTimeSec });
+            if (!recorded) return;
+            const now = Math.floor(Date.now() / 1000);
+            set((state) => ({
+                mediaItems: state.mediaItems.map(item =>
+                    item.id === id ? { ...item, view_count: item.view_count + 1, last_viewed_at: now } : item
+                )
+            }));
+        } catch (e) {
+            console.error("Failed to record view", e);
+        }
+    },
+
+    savePlaybackPosition: async (id, positionSec, durationSec) => {
+        try {
+            const playback = await invoke<Playback | null>('save_playback_position', { id, positionSec, durationSec });
+            if (!playback) return;
+            set((state) => ({
+                mediaItems: state.mediaItems.map(item =>
+                    item.id === id
+                        ? { ...item, playback_position: playback.position_sec || undefined, completed: playback.completed }
+                        : item
+                )
+            }));
+        } catch (e) {
+            console.error("Failed to save playback position", e);
+        }
+    },
+
+    deleteMedia: async (ids) => {
+        try {
+            const report = await invoke<TrashReport>('delete_media', { ids });
+            report.failed.forEach(f => console.error(`Failed to trash ${f.id}: ${f.message}`));
+            set((state) => ({
+                mediaItems: state.mediaItems.filter(item => !report.succeeded.includes(item.id))
+            }));
+            return report.succeeded;
+        } catch (e) {
+            console.error("Failed to delete media", e);
+            return [];
+        }
+    },
+
+    restoreMedia: async (ids) => {
+        const report = await invoke<TrashReport>('restore_media', { ids });
+        await get().fetchMedia(true);
+        return report;
+    },
+
+    // These reject with the backend's message (e.g. "... already exists") so the UI can show it
+    renameMedia: async (id, newName) => {
+        const change = await invoke<PathChange>('rename_media', { id, newName });
+        get().applyPathChanges([change]);
+    },
+
+    batchRenameMedia: async (ids, template, start) => {
+        const changes = await invoke<PathChange[]>('batch_rename_media', { ids, template, start });
+        get().applyPathChanges(changes);
+    },
+
+    moveMedia: async (ids, targetFolder) => {
+        const changes = await invoke<PathChange[]>('move_media', { ids, targetFolder });
+        get().applyPathChanges(changes);
+    },
+
+    applyPathChanges: (changes) => {
+        const byId = new Map(changes.map(c => [c.id, c.to]));
+        set((state) => ({
+            mediaItems: state.mediaItems.map(item =>
+                byId.has(item.id) ? { ...item, path: byId.get(item.id)! } : item
+            )
+        }));
+    },
+
+    getLibraryStats: async () => {
+        return await invoke<LibraryStats>('get_library_stats');
+    },
+
+    checkLibraryHealth: async (deep = false) => {
+        return await invoke<HealthIssue[]>('check_library_health', { deep });
+    },
+
+    relinkMedia: async (ids, searchFolder) => {
+        const report = await invoke<RelinkReport>('relink_media', { ids, searchFolder });
+        get().applyPathChanges(report.relinked);
+        return report;
+    },
+
     setActiveFeed: (feedId) => {
         const { feeds } = get();
+        set({ browsePath: null });
         if (feedId === 'home') {
             set({
                 activeFeedId: feedId,
@@ -218,6 +398,28 @@ export const useAppStore = create<AppState>((set, get) => ({
                     sort_order: 'desc'
                 }
             });
+        } else if (feedId === 'recent') {
+            set({
+                activeFeedId: feedId,
+                mediaItems: [],
+                filters: {
+                    media_type: 'all',
+                    orientation: 'all',
+                    sort_by: 'last_viewed',
+                    sort_order: 'desc'
+                }
+            });
+        } else if (feedId === 'continue') {
+            set({
+                activeFeedId: feedId,
+                mediaItems: [],
+                filters: {
+                    media_type: 'video',
+                    orientation: 'all',
+                    sort_by: 'last_played',
+                    sort_order: 'desc'
+                }
+            });
         } else if (feedId === 'favorites') {
             set({
                 activeFeedId: feedId,
@@ -274,16 +476,48 @@ export const useAppStore = create<AppState>((set, get) => ({
         }
     },
 
-    exportFavorites: async (targetPath) => {
+    exportFeeds: async (targetPath, ids) => {
+        return await invoke<number>('export_feeds', { targetPath, ids });
+    },
+
+    inspectFeedImport: async (path) => {
+        return await invoke<FeedImportPreview>('inspect_feed_import', { path });
+    },
+
+    importFeeds: async (path, folderMap) => {
+        const count = await invoke<number>('import_feeds', { path, folderMap });
+        await get().loadFeeds();
+        return count;
+    },
+
+    exportFavorites: async (targetPath, collision = 'rename') => {
         try {
-            const count = await invoke<number>('export_starred', { targetPath });
-            return count;
+            const report = await invoke<ExportReport>('export_starred', { targetPath, collision });
+            return report;
         } catch (e) {
             console.error("Failed to export favorites", e);
             throw e;
         }
     },
 
+    exportMedia: async (source, targetPath, options = {}) => {
+        try {
+            return await invoke<ExportReport>('export_media', { source, targetPath, options });
+        } catch (e) {
+            console.error("Failed to export media", e);
+            throw e;
+        }
+    },
+
+    exportPlaylist: async (source, targetPath, options) => {
+        try {
+            return await invoke<number>('export_playlist', { source, targetPath, options });
+        } catch (e) {
+            console.error("Failed to export playlist", e);
+            throw e;
+        }
+    },
+
     clearFavorites: async () => {
         try {
             await invoke('clear_favorites');
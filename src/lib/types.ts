@@ -8,13 +8,57 @@ export interface MediaItem {
     height?: number;
     duration_sec?: number;
     starred: boolean;
+    is_animated: boolean;
+    frame_count?: number;
+    loop_count?: number; // 0 loops forever
+    blurhash?: string;
+    dominant_color?: string; // "#rrggbb"
+    average_color?: string;
+    colorfulness?: number;
+    view_count: number;
+    last_viewed_at?: number;
+    playback_position?: number; // Seconds to resume a long video from
+    completed: boolean;         // Watched to the end at least once
     thumbnail_path?: string;
 }
 
-export interface Folder {
+export interface Library {
+    id: string;
+    name: string;
+    db_path: string;
+    external: boolean;  // Database opened from elsewhere; deleting the library keeps the file
+}
+
+export interface LibraryRegistry {
+    active: string;
+    libraries: Library[];
+}
+
+export interface FolderSettings {
+    is_active: boolean;          // Inactive folders are hidden everywhere but stay indexed
+    recursive: boolean;
+    max_depth: number | null;    // Subfolder levels below the root; null for no limit
+    include_hidden: boolean;
+    follow_symlinks: boolean;
+    rescan_interval_min: number | null;
+    exclude_patterns: string[];  // gitignore-style, relative to the folder
+}
+
+export interface Folder extends FolderSettings {
     id: number;
     path: string;
+    last_scanned_at: number | null;
+}
+
+export interface FolderNode {
+    folder_id: number;
+    directory_id: number | null;
+    path: string;
+    name: string;
     is_active: boolean;
+    item_count: number;   // Directly inside this folder
+    total_count: number;  // Including subfolders
+    children: FolderNode[];
 }
 
 export interface Feed {
@@ -25,7 +69,7 @@ export interface Feed {
 }
 
 export interface FilterOptions {
-    media_type?: "image" | "video" | "all";
+    media_type?: "image" | "video" | "animated" | "all";
     orientation?: "horizontal" | "vertical" | "square" | "all";
     min_width?: number;
     min_height?: number;
@@ -36,6 +80,208 @@ export interface FilterOptions {
     extensions?: string[];
     folder_paths?: string[]; // Selection of folders for this feed
     favorites_only?: boolean;
-    sort_by?: "created_at" | "size_bytes" | "resolution" | "duration_sec" | "filename" | "random";
+    color?: string; // "#rrggbb", matched against each item's dominant color
+    color_tolerance?: number; // CIELAB ΔE, 20 when unset
+    monochrome?: boolean; // true: grayscale only, false: color only
+    unseen?: boolean; // true: never viewed, false: viewed at least once
+    not_viewed_days?: number; // Leaves out items viewed within this many days
+    in_progress?: boolean; // true: videos left off partway, false: everything else
+    sort_by?: "created_at" | "size_bytes" | "resolution" | "duration_sec" | "filename" | "hue" | "view_count" | "last_viewed" | "last_played" | "random" | "weighted";
     sort_order?: "asc" | "desc";
+    shuffle_seed?: number; // Keeps "random" and "weighted" in the same order across pages
+    shuffle_weights?: ShuffleWeights;
+    shuffle_as_of?: number; // Unix seconds; "weighted" ignores views after this, so paging doesn't reorder it
+}
+
+// Multipliers for the weighted shuffle: 1 is neutral, above boosts, below penalizes
+export interface ShuffleWeights {
+    unseen: number;
+    starred: number;
+    recent: number;           // Added within recent_days
+    recent_days: number;
+    recently_viewed: number;  // Viewed within viewed_days
+    viewed_days: number;
+}
+
+export type CollisionPolicy = "skip" | "overwrite" | "rename" | "prefix_folder";
+
+export type ExportSource =
+    | { filters: FilterOptions }
+    | { feed: number }
+    | "favorites"
+    | { ids: number[] };
+
+export type ExportMode = "copy" | "move" | "hardlink" | "symlink" | "zip";
+
+export interface ImageTransform {
+    max_long_edge?: number;
+    format?: "jpeg" | "png" | "webp";
+    quality?: number; // 1-100, JPEG only
+    strip_metadata?: boolean;
+}
+
+export interface ExportOptions {
+    mode?: ExportMode; // "zip" expects an archive file path as the target
+    collision?: CollisionPolicy;
+    preserve_structure?: boolean;
+    manifest?: "json" | "csv";
+    transform?: ImageTransform; // copy and zip modes only
+}
+
+export interface ExportProgress {
+    done: number;
+    total: number;
+    current: string;
+}
+
+export interface ExportFileResult {
+    id?: number;
+    source: string;
+    destination?: string;
+    status: "exported" | "skipped" | "failed";
+    message?: string;
+}
+
+export interface ExportReport {
+    exported: number;
+    skipped: number;
+    failed: number;
+    files: ExportFileResult[];
+    manifest_path?: string;
+    left_library: number; // moved files now outside every library folder
+}
+
+export interface PlaylistOptions {
+    format: "m3u8" | "xspf";
+    paths?: "absolute" | "relative"; // relative to the playlist's folder
+    title?: string;
+}
+
+export interface TrashedItem {
+    id: number;
+    original_path: string;
+    trash_path: string;
+    file_type: "image" | "video";
+    size_bytes: number;
+    trashed_at: number;
+}
+
+export interface TrashReport {
+    succeeded: number[];
+    failed: { id: number; message: string }[];
+}
+
+export interface PathChange {
+    id: number;
+    from: string;
+    to: string;
+}
+
+export interface ServerConfig {
+    enabled: boolean;
+    bind_addresses: string[];
+    port: number;
+    token: string;
+}
+
+export interface ServerStatus {
+    running: boolean;
+    urls: string[];
+    config: ServerConfig;
+}
+
+export interface FolderMapping {
+    source: string;
+    found: boolean; // already a library folder here
+    suggestion?: string;
+}
+
+export interface FeedImportPreview {
+    feeds: { name: string; folder_paths: string[]; error?: string }[];
+    folders: FolderMapping[];
+}
+
+export interface BackupInfo {
+    path: string;
+    created_at: number;
+    size_bytes: number;
+}
+
+export interface BackupSchedule {
+    interval_hours: number; // 0 = no scheduled backups
+    keep: number;
+}
+
+export interface OrphanReport {
+    outside_folders: number; // kept: the file still exists
+    missing_files: number;
+    thumbnails: number;
+}
+
+export interface StatBucket {
+    key: string;
+    count: number;
+    bytes: number;
+}
+
+export interface LibraryStats {
+    totals: {
+        items: number;
+        images: number;
+        videos: number;
+        starred: number;
+        total_bytes: number;
+        folders: number;
+        feeds: number;
+    };
+    favorites_share: number; // 0-1
+    favorites_bytes: number;
+    by_folder: StatBucket[];
+    by_extension: StatBucket[];
+    by_type: StatBucket[];
+    by_resolution: StatBucket[];
+    by_duration: StatBucket[];
+    by_month: StatBucket[]; // "YYYY-MM" the item was added to the library (older items: file creation)
+    largest: MediaItem[];
+    missing_metadata: { dimensions: number; video_duration: number };
+}
+
+export interface HealthIssue {
+    id: number;
+    path: string;
+    file_type: "image" | "video";
+    status: "offline" | "missing" | "unreadable" | "corrupt";
+    message?: string;
+}
+
+export interface RelinkReport {
+    relinked: { id: number; from: string; to: string; matched_by: "hash" | "name_size" }[];
+    ambiguous: number[];
+    not_found: number[];
+}
+
+export interface RemapReport {
+    folders: number;
+    items: number;
+    feeds: number;
+}
+
+export interface FormatInfo {
+    id: string;
+    extensions: string[];
+    mime: string;
+    kind: "image" | "video";
+    enabled: boolean;
+}
+
+export interface Playback {
+    position_sec: number;
+    completed: boolean;
+}
+
+export interface HistoryStatus {
+    enabled: boolean;
+    viewed_items: number;
+    total_views: number;
+    total_watch_time_sec: number;
 }